@@ -1,28 +1,6 @@
 use std::io::stdin;
 
-use solution::seg_tree::*;
-use solution::traits::monoid::*;
-use solution::traits::semigroup::*;
-
-#[derive(Clone)]
-struct Plus(i64);
-impl Semigroup for Plus {
-    #[inline]
-    fn merge(self, other: Self) -> Self {
-        Plus(self.0 + other.0)
-    }
-}
-impl Monoid for Plus {
-    #[inline]
-    fn empty() -> Self {
-        Plus(0)
-    }
-}
-impl Applier<(Sum<i64>, Size)> for Plus {
-    fn apply(&self, (Sum(s), n): (Sum<i64>, Size)) -> (Sum<i64>, Size) {
-        (Sum(s + self.0 * n.0 as i64), n)
-    }
-}
+use solution::seg_tree::{monoids::*, *};
 
 fn main() {
     let mut buf = String::new();
@@ -46,7 +24,7 @@ fn main() {
         .collect();
     buf.clear();
 
-    let mut tree: SegTree<_, Plus> =
+    let mut tree: SegTree<_, Plus<i64>> =
         SegTree::build(len as usize, |i| (Sum(init[i]), Size::default()));
 
     for _i in 0..num_commands {
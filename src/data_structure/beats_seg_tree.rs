@@ -0,0 +1,287 @@
+use crate::traits::{
+    semigroup::{Max, Min},
+    Monoid,
+};
+
+use alloc::{vec, vec::Vec};
+use core::{
+    cmp::{max, min},
+    ops::{Range, RangeBounds},
+};
+
+/// Segment Tree Beats over `i64`, supporting range `chmin`/`chmax` with range sum
+///
+/// Each node keeps its maximum, the strict second maximum and how many
+/// values equal the maximum (the same for the minimum), so a `chmin` that
+/// only lowers the maximums of a node can be applied to it as a whole.
+/// Otherwise it descends further, which is amortized to O(log² n) per
+/// operation. Like [`MutSegTree`](super::mut_seg_tree::MutSegTree) it is
+/// updated in place.
+#[derive(Clone, Debug)]
+pub struct BeatsSegTree {
+    len: usize,
+    /// `nodes[1]` is the root, `nodes[k]` has children `2k` and `2k + 1`
+    nodes: Vec<Node>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    sum: i64,
+    max: i64,
+    /// The largest value below `max`, `i64::MIN` if there is none
+    second_max: i64,
+    max_count: i64,
+    min: i64,
+    /// The smallest value above `min`, `i64::MAX` if there is none
+    second_min: i64,
+    min_count: i64,
+}
+
+impl Node {
+    fn leaf(v: i64) -> Self {
+        Node {
+            sum: v,
+            max: v,
+            second_max: i64::MIN,
+            max_count: 1,
+            min: v,
+            second_min: i64::MAX,
+            min_count: 1,
+        }
+    }
+
+    fn merge(left: &Self, right: &Self) -> Self {
+        let (max_value, second_max, max_count) = if left.max == right.max {
+            (
+                left.max,
+                max(left.second_max, right.second_max),
+                left.max_count + right.max_count,
+            )
+        } else if left.max > right.max {
+            (left.max, max(left.second_max, right.max), left.max_count)
+        } else {
+            (right.max, max(left.max, right.second_max), right.max_count)
+        };
+        let (min_value, second_min, min_count) = if left.min == right.min {
+            (
+                left.min,
+                min(left.second_min, right.second_min),
+                left.min_count + right.min_count,
+            )
+        } else if left.min < right.min {
+            (left.min, min(left.second_min, right.min), left.min_count)
+        } else {
+            (right.min, min(left.min, right.second_min), right.min_count)
+        };
+        Node {
+            sum: left.sum + right.sum,
+            max: max_value,
+            second_max,
+            max_count,
+            min: min_value,
+            second_min,
+            min_count,
+        }
+    }
+
+    /// Lower the maximums to `x`, requires `second_max < x`
+    fn chmin(&mut self, x: i64) {
+        if self.max <= x {
+            return;
+        }
+        self.sum += (x - self.max) * self.max_count;
+        if self.min == self.max {
+            self.min = x;
+        } else if self.second_min == self.max {
+            self.second_min = x;
+        }
+        self.max = x;
+    }
+
+    /// Raise the minimums to `x`, requires `x < second_min`
+    fn chmax(&mut self, x: i64) {
+        if x <= self.min {
+            return;
+        }
+        self.sum += (x - self.min) * self.min_count;
+        if self.max == self.min {
+            self.max = x;
+        } else if self.second_max == self.min {
+            self.second_max = x;
+        }
+        self.min = x;
+    }
+}
+
+impl BeatsSegTree {
+    pub fn build<F: Fn(usize) -> i64>(len: usize, init: F) -> Self {
+        let mut tree = BeatsSegTree {
+            len,
+            nodes: vec![Node::leaf(0); max(4 * len, 1)],
+        };
+        if len > 0 {
+            tree.build_inner(1, 0..len, &init);
+        }
+        tree
+    }
+
+    fn build_inner<F: Fn(usize) -> i64>(&mut self, k: usize, range: Range<usize>, init: &F) {
+        if range.len() == 1 {
+            self.nodes[k] = Node::leaf(init(range.start));
+            return;
+        }
+        let mid = (range.start + range.end) / 2;
+        self.build_inner(2 * k, range.start..mid, init);
+        self.build_inner(2 * k + 1, mid..range.end, init);
+        self.update(k);
+    }
+
+    pub fn size(&self) -> usize {
+        self.len
+    }
+
+    fn resolve_range(&self, range: impl RangeBounds<usize>) -> Range<usize> {
+        super::range::resolve_range(range, self.size(), "tree")
+    }
+
+    /// Replace every `a[i]` in `range` by `min(a[i], x)`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn chmin(&mut self, range: impl RangeBounds<usize>, x: i64) {
+        let range = self.resolve_range(range);
+        if !range.is_empty() {
+            self.chmin_inner(1, 0..self.len, &range, x);
+        }
+    }
+
+    fn chmin_inner(&mut self, k: usize, node: Range<usize>, range: &Range<usize>, x: i64) {
+        if node.end <= range.start || range.end <= node.start || self.nodes[k].max <= x {
+            return;
+        }
+        if range.start <= node.start && node.end <= range.end && self.nodes[k].second_max < x {
+            self.nodes[k].chmin(x);
+            return;
+        }
+        self.push(k);
+        let mid = (node.start + node.end) / 2;
+        self.chmin_inner(2 * k, node.start..mid, range, x);
+        self.chmin_inner(2 * k + 1, mid..node.end, range, x);
+        self.update(k);
+    }
+
+    /// Replace every `a[i]` in `range` by `max(a[i], x)`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn chmax(&mut self, range: impl RangeBounds<usize>, x: i64) {
+        let range = self.resolve_range(range);
+        if !range.is_empty() {
+            self.chmax_inner(1, 0..self.len, &range, x);
+        }
+    }
+
+    fn chmax_inner(&mut self, k: usize, node: Range<usize>, range: &Range<usize>, x: i64) {
+        if node.end <= range.start || range.end <= node.start || x <= self.nodes[k].min {
+            return;
+        }
+        if range.start <= node.start && node.end <= range.end && x < self.nodes[k].second_min {
+            self.nodes[k].chmax(x);
+            return;
+        }
+        self.push(k);
+        let mid = (node.start + node.end) / 2;
+        self.chmax_inner(2 * k, node.start..mid, range, x);
+        self.chmax_inner(2 * k + 1, mid..node.end, range, x);
+        self.update(k);
+    }
+
+    /// The sum of `range`, `0` for an empty range
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn query_sum(&mut self, range: impl RangeBounds<usize>) -> i64 {
+        self.query(range).sum
+    }
+
+    /// The maximum of `range`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn query_max(&mut self, range: impl RangeBounds<usize>) -> Max<i64> {
+        let node = self.query(range);
+        if node.max_count == 0 {
+            Max::empty()
+        } else {
+            Max::Has(node.max)
+        }
+    }
+
+    /// The minimum of `range`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn query_min(&mut self, range: impl RangeBounds<usize>) -> Min<i64> {
+        let node = self.query(range);
+        if node.min_count == 0 {
+            Min::empty()
+        } else {
+            Min::Has(node.min)
+        }
+    }
+
+    /// The merged node of `range`, with zero counts if it is empty
+    fn query(&mut self, range: impl RangeBounds<usize>) -> Node {
+        let range = self.resolve_range(range);
+        let empty = Node {
+            sum: 0,
+            max: i64::MIN,
+            second_max: i64::MIN,
+            max_count: 0,
+            min: i64::MAX,
+            second_min: i64::MAX,
+            min_count: 0,
+        };
+        if range.is_empty() {
+            return empty;
+        }
+        self.query_inner(1, 0..self.len, &range, empty)
+    }
+
+    fn query_inner(
+        &mut self,
+        k: usize,
+        node: Range<usize>,
+        range: &Range<usize>,
+        acc: Node,
+    ) -> Node {
+        if node.end <= range.start || range.end <= node.start {
+            return acc;
+        }
+        if range.start <= node.start && node.end <= range.end {
+            return Node::merge(&acc, &self.nodes[k]);
+        }
+        self.push(k);
+        let mid = (node.start + node.end) / 2;
+        let acc = self.query_inner(2 * k, node.start..mid, range, acc);
+        self.query_inner(2 * k + 1, mid..node.end, range, acc)
+    }
+
+    fn update(&mut self, k: usize) {
+        self.nodes[k] = Node::merge(&self.nodes[2 * k], &self.nodes[2 * k + 1]);
+    }
+
+    /// Pass the bounds of `k` down, the children never go beyond them
+    fn push(&mut self, k: usize) {
+        let Node { max, min, .. } = self.nodes[k];
+        for child in [2 * k, 2 * k + 1] {
+            self.nodes[child].chmin(max);
+            self.nodes[child].chmax(min);
+        }
+    }
+}
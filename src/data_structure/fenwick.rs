@@ -0,0 +1,137 @@
+use super::seg_tree::{Applier, SegTree};
+use crate::traits::*;
+
+use alloc::vec::Vec;
+use core::ops::{Range, RangeBounds};
+
+/// A Fenwick tree (binary indexed tree): point updates and prefix queries
+///
+/// A single `Vec` of `n` values, with O(log n) [`Fenwick::add`] and
+/// [`Fenwick::prefix_sum`] that are lighter and faster than a `SegTree`,
+/// for when nothing more is needed. Values only ever get merged into
+/// prefixes, so they have to be commutative, e.g. `Sum` or `Xor`.
+/// Arbitrary ranges are prefixes with a shorter prefix taken away, so
+/// [`Fenwick::query`] and [`Fenwick::to_seg_tree`] need a [`Group`] for
+/// the inverses. Updates are in place, unlike the persistent `SegTree`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fenwick<T> {
+    /// `tree[i]` is the merged value of `(i & (i + 1))..=i`
+    tree: Vec<T>,
+}
+
+impl<T> Fenwick<T> {
+    pub fn size(&self) -> usize {
+        self.tree.len()
+    }
+
+    fn resolve_range(&self, range: impl RangeBounds<usize>) -> Range<usize> {
+        super::range::resolve_range(range, self.size(), "tree")
+    }
+}
+
+impl<T: CommutativeSemigroup + Monoid + Clone> Fenwick<T> {
+    /// `len` values, all `T::empty()`
+    pub fn new(len: usize) -> Self {
+        Fenwick {
+            tree: (0..len).map(|_| T::empty()).collect(),
+        }
+    }
+
+    /// Merge `delta` into the value at `index` in O(log n)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= self.size()`.
+    pub fn add(&mut self, index: usize, delta: T) {
+        assert!(
+            index < self.size(),
+            "index {} out of bounds for tree of size {}",
+            index,
+            self.size()
+        );
+        let mut i = index;
+        while i < self.size() {
+            self.tree[i] = T::merge(self.tree[i].clone(), delta.clone());
+            i |= i + 1;
+        }
+    }
+
+    /// The merged value of the first `n` values, i.e. of `..n`, in O(log n)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n > self.size()`.
+    pub fn prefix_sum(&self, n: usize) -> T {
+        assert!(
+            n <= self.size(),
+            "prefix of {} out of bounds for tree of size {}",
+            n,
+            self.size()
+        );
+        let mut acc = T::empty();
+        let mut end = n;
+        while end > 0 {
+            acc = T::merge(acc, self.tree[end - 1].clone());
+            end &= end - 1;
+        }
+        acc
+    }
+
+    /// Build from the values of `tree`, with its pending modifiers applied, in O(n)
+    pub fn from_seg_tree<M: Applier<T> + Monoid + Clone>(tree: &SegTree<T, M>) -> Self {
+        tree.iter().collect()
+    }
+}
+
+impl<T: Group + CommutativeSemigroup + Clone> Fenwick<T> {
+    /// Query the merged value of a range, e.g. `start..end`, `..=end` or `..`
+    ///
+    /// The prefix before `start` is taken away from the one up to `end`,
+    /// with [`Group::inverse`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn query(&self, range: impl RangeBounds<usize>) -> T {
+        let Range { start, end } = self.resolve_range(range);
+        T::merge(self.prefix_sum(end), self.prefix_sum(start).inverse())
+    }
+
+    /// The values, recovered in O(n)
+    pub fn to_vec(&self) -> Vec<T> {
+        // undo the build of `From<Vec<T>>`, last step first
+        let mut values = self.tree.clone();
+        for i in (0..values.len()).rev() {
+            let j = i | (i + 1);
+            if j < values.len() {
+                values[j] = T::merge(values[j].clone(), values[i].clone().inverse());
+            }
+        }
+        values
+    }
+
+    /// A `SegTree` of the same values, in O(n)
+    pub fn to_seg_tree<M: Applier<T> + Monoid + Clone>(&self) -> SegTree<T, M> {
+        SegTree::from(self.to_vec())
+    }
+}
+
+impl<T: CommutativeSemigroup + Clone> From<Vec<T>> for Fenwick<T> {
+    /// Build in O(n), each value is merged into the next covering one once
+    fn from(mut tree: Vec<T>) -> Self {
+        for i in 0..tree.len() {
+            let j = i | (i + 1);
+            if j < tree.len() {
+                tree[j] = T::merge(tree[j].clone(), tree[i].clone());
+            }
+        }
+        Fenwick { tree }
+    }
+}
+
+impl<T: CommutativeSemigroup + Clone> core::iter::FromIterator<T> for Fenwick<T> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
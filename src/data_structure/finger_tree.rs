@@ -17,8 +17,16 @@ impl<T: Clone> Measured for Value<T> {
     }
 }
 
-pub trait Ref<A>: AsRef<A> + std::ops::Deref<Target = A> + Clone {
+pub trait Ref<A>: AsRef<A> + core::ops::Deref<Target = A> + Clone {
     fn new(a: A) -> Self;
+
+    /// The inner value if this is its only reference, `this` otherwise
+    fn try_unwrap(this: Self) -> Result<A, Self>;
+
+    /// Mutable access to the inner value, cloning it first if it is shared
+    fn make_mut(this: &mut Self) -> &mut A
+    where
+        A: Clone;
 }
 
 pub trait TreeRef<V: Measured>: Sized + Clone {
@@ -27,22 +35,35 @@ pub trait TreeRef<V: Measured>: Sized + Clone {
 }
 
 impl<A: Measured> TreeRef<A> for RcRef {
-    type NodeRef = std::rc::Rc<Node<A, Self>>;
-    type TreeRef = std::rc::Rc<FingerTree<A, Self>>;
+    type NodeRef = alloc::rc::Rc<Node<A, Self>>;
+    type TreeRef = alloc::rc::Rc<FingerTree<A, Self>>;
 }
 impl<A: Measured> TreeRef<A> for ArcRef {
-    type NodeRef = std::sync::Arc<Node<A, Self>>;
-    type TreeRef = std::sync::Arc<FingerTree<A, Self>>;
+    type NodeRef = alloc::sync::Arc<Node<A, Self>>;
+    type TreeRef = alloc::sync::Arc<FingerTree<A, Self>>;
 }
 
-impl<A> Ref<A> for std::rc::Rc<A> {
+impl<A> Ref<A> for alloc::rc::Rc<A> {
     #[inline]
     fn new(a: A) -> Self {
-        std::rc::Rc::new(a)
+        alloc::rc::Rc::new(a)
+    }
+
+    #[inline]
+    fn try_unwrap(this: Self) -> Result<A, Self> {
+        alloc::rc::Rc::try_unwrap(this)
+    }
+
+    #[inline]
+    fn make_mut(this: &mut Self) -> &mut A
+    where
+        A: Clone,
+    {
+        alloc::rc::Rc::make_mut(this)
     }
 }
 
-impl<A: Measured> Measured for std::rc::Rc<A> {
+impl<A: Measured> Measured for alloc::rc::Rc<A> {
     type To = A::To;
 
     fn measure(&self) -> Self::To {
@@ -50,14 +71,27 @@ impl<A: Measured> Measured for std::rc::Rc<A> {
     }
 }
 
-impl<A> Ref<A> for std::sync::Arc<A> {
+impl<A> Ref<A> for alloc::sync::Arc<A> {
     #[inline]
     fn new(a: A) -> Self {
-        std::sync::Arc::new(a)
+        alloc::sync::Arc::new(a)
+    }
+
+    #[inline]
+    fn try_unwrap(this: Self) -> Result<A, Self> {
+        alloc::sync::Arc::try_unwrap(this)
+    }
+
+    #[inline]
+    fn make_mut(this: &mut Self) -> &mut A
+    where
+        A: Clone,
+    {
+        alloc::sync::Arc::make_mut(this)
     }
 }
 
-impl<A: Measured> Measured for std::sync::Arc<A> {
+impl<A: Measured> Measured for alloc::sync::Arc<A> {
     type To = A::To;
 
     fn measure(&self) -> Self::To {
@@ -78,9 +112,9 @@ pub trait PersistMonoidIndexDeque<A: Measured>: Measured {
 //-------------------
 // Struct & Enums
 //-------------------
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct RcRef;
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct ArcRef;
 
 #[derive(Clone)]
@@ -148,7 +182,7 @@ where
 #[derive(Clone)]
 pub struct FingerTree<A: Measured, R: TreeRef<A> = RcRef>(FingerTreeInner<A, R>);
 
-impl<A: Measured, R: TreeRef<A>> std::iter::FromIterator<A> for FingerTree<A, R> {
+impl<A: Measured, R: TreeRef<A>> core::iter::FromIterator<A> for FingerTree<A, R> {
     #[inline]
     fn from_iter<T: IntoIterator<Item = A>>(iter: T) -> Self {
         FingerTree(FingerTreeInner::push_many_r(
@@ -166,12 +200,13 @@ impl<A: Measured, R: TreeRef<A>> PersistMonoidIndexDeque<A> for FingerTree<A, R>
     }
 
     fn split<F: Fn(&A::To) -> bool>(&self, pred: F) -> Option<(Self, A, Self)> {
-        self.0.clone().split_offset(A::To::empty(), &pred).and_then(
-            |(front, mid, back)| match &*mid {
-                Node(NodeInner::Leaf(a)) => Some((FingerTree(front), a.clone(), FingerTree(back))),
+        self.0
+            .clone()
+            .split_offset(A::To::empty(), &pred)
+            .map(|(front, mid, back)| match &*mid {
+                Node(NodeInner::Leaf(a)) => (FingerTree(front), a.clone(), FingerTree(back)),
                 _ => panic!("not the shallowest tree layer"),
-            },
-        )
+            })
     }
 
     fn concat(&self, other: &Self) -> Self {
@@ -193,15 +228,15 @@ impl<A: Measured, R: TreeRef<A>> PersistMonoidIndexDeque<A> for FingerTree<A, R>
     }
 
     fn view_l(&self) -> Option<(A, Self)> {
-        self.0.clone().view_l().and_then(|(a, tree)| match &*a {
-            Node(NodeInner::Leaf(a)) => Some((a.clone(), FingerTree(tree))),
+        self.0.clone().view_l().map(|(a, tree)| match &*a {
+            Node(NodeInner::Leaf(a)) => (a.clone(), FingerTree(tree)),
             _ => panic!("not the shallowest tree layer"),
         })
     }
 
     fn view_r(&self) -> Option<(Self, A)> {
-        self.0.clone().view_r().and_then(|(tree, a)| match &*a {
-            Node(NodeInner::Leaf(a)) => Some((FingerTree(tree), a.clone())),
+        self.0.clone().view_r().map(|(tree, a)| match &*a {
+            Node(NodeInner::Leaf(a)) => (FingerTree(tree), a.clone()),
             _ => panic!("not the shallowest tree layer"),
         })
     }
@@ -312,7 +347,7 @@ impl<A> IntoIterator for Digit<A> {
     }
 }
 
-impl<A> std::iter::FromIterator<A> for Digit<A> {
+impl<A> core::iter::FromIterator<A> for Digit<A> {
     fn from_iter<T: IntoIterator<Item = A>>(iter: T) -> Self {
         let mut ret = None;
         for a in iter {
@@ -724,7 +759,7 @@ where
     }
 
     fn concat(front: Self, back: Self) -> Self {
-        Self::concat_3_way(front, &mut std::iter::empty(), back)
+        Self::concat_3_way(front, &mut core::iter::empty(), back)
     }
 
     fn concat_3_way(front: Self, mid: &mut dyn Iterator<Item = R::NodeRef>, back: Self) -> Self {
@@ -0,0 +1,126 @@
+use crate::traits::{semigroup::Min, Semigroup};
+
+use alloc::rc::Rc;
+use core::ops::RangeInclusive;
+
+/// A persistent Li Chao tree, the minimum of lines `y = a * x + b` at a point
+///
+/// It covers the integers `x` of a fixed domain `lo..=hi`, and only the
+/// nodes on the path of an inserted line are allocated, so the domain may be
+/// as large as `i64` allows. [`LiChaoTree::add_line`] returns a new tree and
+/// cloning is cheap, like [`SegTree`](super::seg_tree::SegTree).
+///
+/// `a * x + b` has to fit in `i64` for every `x` of the domain.
+#[derive(Clone, Debug)]
+pub struct LiChaoTree {
+    lo: i64,
+    hi: i64,
+    root: Option<Rc<Node>>,
+}
+
+#[derive(Debug)]
+struct Node {
+    /// The lowest line at the middle of this node, `(a, b)`
+    line: (i64, i64),
+    left: Option<Rc<Node>>,
+    right: Option<Rc<Node>>,
+}
+
+fn eval((a, b): (i64, i64), x: i64) -> i64 {
+    a * x + b
+}
+
+/// `(lo + hi) / 2` rounded down, without overflow
+fn middle(lo: i64, hi: i64) -> i64 {
+    (lo >> 1) + (hi >> 1) + (lo & hi & 1)
+}
+
+impl LiChaoTree {
+    /// An empty tree over the `x` in `domain`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `domain` is empty.
+    pub fn new(domain: RangeInclusive<i64>) -> Self {
+        let (lo, hi) = domain.into_inner();
+        assert!(lo <= hi, "domain {}..={} is empty", lo, hi);
+        LiChaoTree { lo, hi, root: None }
+    }
+
+    pub fn domain(&self) -> RangeInclusive<i64> {
+        self.lo..=self.hi
+    }
+
+    /// Insert the line `y = a * x + b` in O(log(hi - lo)), returning the new tree
+    ///
+    /// When two lines are equal at the middle of a node the one inserted
+    /// earlier stays there. This only decides where the lines are stored,
+    /// [`LiChaoTree::query`] gives the same minimum either way.
+    pub fn add_line(&self, a: i64, b: i64) -> Self {
+        LiChaoTree {
+            root: Some(Self::insert(self.root.as_ref(), self.lo, self.hi, (a, b))),
+            ..*self
+        }
+    }
+
+    fn insert(node: Option<&Rc<Node>>, lo: i64, hi: i64, line: (i64, i64)) -> Rc<Node> {
+        let node = match node {
+            None => {
+                return Rc::new(Node {
+                    line,
+                    left: None,
+                    right: None,
+                })
+            }
+            Some(node) => node,
+        };
+        let mid = middle(lo, hi);
+        // keep the lower one at `mid`, the other can only win on one side
+        let (keep, other) = if eval(line, mid) < eval(node.line, mid) {
+            (line, node.line)
+        } else {
+            (node.line, line)
+        };
+        let (mut left, mut right) = (node.left.clone(), node.right.clone());
+        if lo < mid && eval(other, lo) < eval(keep, lo) {
+            left = Some(Self::insert(left.as_ref(), lo, mid, other));
+        } else if mid < hi && eval(other, hi) < eval(keep, hi) {
+            right = Some(Self::insert(right.as_ref(), mid + 1, hi, other));
+        }
+        Rc::new(Node {
+            line: keep,
+            left,
+            right,
+        })
+    }
+
+    /// The minimum `a * x + b` over the inserted lines, `Min::Inf` if there is none
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is outside of the domain.
+    pub fn query(&self, x: i64) -> Min<i64> {
+        assert!(
+            self.lo <= x && x <= self.hi,
+            "x = {} is outside of the domain {}..={}",
+            x,
+            self.lo,
+            self.hi
+        );
+        let (mut lo, mut hi) = (self.lo, self.hi);
+        let mut node = self.root.as_ref();
+        let mut result = Min::Inf;
+        while let Some(n) = node {
+            result = result.merge(Min::Has(eval(n.line, x)));
+            let mid = middle(lo, hi);
+            if x <= mid {
+                hi = mid;
+                node = n.left.as_ref();
+            } else {
+                lo = mid + 1;
+                node = n.right.as_ref();
+            }
+        }
+        result
+    }
+}
@@ -1,3 +1,13 @@
+pub mod beats_seg_tree;
+pub mod fenwick;
 pub mod finger_tree;
+#[cfg(feature = "std")]
 pub mod lct;
+pub mod li_chao;
+pub mod mut_seg_tree;
+pub mod persistent_array;
+mod range;
 pub mod seg_tree;
+pub mod seg_tree_2d;
+pub mod sparse_seg_tree;
+pub mod sparse_table;
@@ -0,0 +1,179 @@
+use super::seg_tree::Applier;
+use crate::traits::*;
+
+use alloc::vec::Vec;
+use core::ops::{Range, RangeBounds};
+
+/// An in-place segment tree with lazy propagation, backed by flat `Vec`s
+///
+/// Unlike the persistent `SegTree`, `apply` mutates the tree instead of
+/// returning a new version, so old versions are lost. In return it does no
+/// allocation after `build` and is several times faster, pick it whenever
+/// persistence is not needed.
+///
+/// The leaves are padded with `V::empty()` up to a power of two.
+#[derive(Clone, Debug)]
+pub struct MutSegTree<V, M> {
+    len: usize,
+    log: u32,
+    /// `values[1]` is the root, `values[k]` has children `2k` and `2k + 1`,
+    /// the leaves start at `values[1 << log]`
+    values: Vec<V>,
+    /// Pending modifiers of the inner nodes
+    modifiers: Vec<M>,
+}
+
+impl<V, M> MutSegTree<V, M> {
+    pub fn size(&self) -> usize {
+        self.len
+    }
+
+    fn resolve_range(&self, range: impl RangeBounds<usize>) -> Range<usize> {
+        super::range::resolve_range(range, self.size(), "tree")
+    }
+}
+
+impl<V: Monoid + Clone, M: Applier<V> + Monoid + Clone> MutSegTree<V, M> {
+    pub fn build<F: Fn(usize) -> V>(len: usize, init: F) -> Self {
+        let mut tree = Self::with_capacity(len);
+        tree.rebuild(len, init);
+        tree
+    }
+
+    /// An empty tree that can be [`rebuild`](Self::rebuild) with up to
+    /// `capacity` values without allocating
+    ///
+    /// With `w = capacity.next_power_of_two()`, exactly `2 * w` values and
+    /// `w` modifiers are allocated.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let width = capacity.next_power_of_two();
+        MutSegTree {
+            len: 0,
+            log: 0,
+            values: Vec::with_capacity(2 * width),
+            modifiers: Vec::with_capacity(width),
+        }
+    }
+
+    /// Replace the whole tree by `len` values `init(i)`, reusing the storage
+    ///
+    /// It only allocates if `len` needs more than what is already allocated,
+    /// e.g. by [`with_capacity`](Self::with_capacity), handy for many small
+    /// trees in a multi-testcase loop.
+    pub fn rebuild<F: Fn(usize) -> V>(&mut self, len: usize, init: F) {
+        let log = len.next_power_of_two().trailing_zeros();
+        let width = 1 << log;
+        self.len = len;
+        self.log = log;
+
+        self.values.clear();
+        self.values.reserve_exact(2 * width);
+        self.values.resize(width, V::empty());
+        self.values.extend((0..len).map(init));
+        self.values.resize(2 * width, V::empty());
+
+        self.modifiers.clear();
+        self.modifiers.resize(width, M::empty());
+
+        for k in (1..width).rev() {
+            self.update(k);
+        }
+    }
+
+    /// Query the merged value of a range, e.g. `start..end`, `..=end` or `..`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn query(&mut self, range: impl RangeBounds<usize>) -> V {
+        let Range { start, end } = self.resolve_range(range);
+        if start == end {
+            return V::empty();
+        }
+        let (mut l, mut r) = (start + (1 << self.log), end + (1 << self.log));
+        self.push_boundary(l, r);
+
+        let (mut left, mut right) = (V::empty(), V::empty());
+        while l < r {
+            if l & 1 == 1 {
+                left = V::merge(left, self.values[l].clone());
+                l += 1;
+            }
+            if r & 1 == 1 {
+                r -= 1;
+                right = V::merge(self.values[r].clone(), right);
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+        V::merge(left, right)
+    }
+
+    /// Apply a modifier to a range, e.g. `start..end`, `..=end` or `..`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn apply(&mut self, range: impl RangeBounds<usize>, m: M) {
+        let Range { start, end } = self.resolve_range(range);
+        if start == end {
+            return;
+        }
+        let (l, r) = (start + (1 << self.log), end + (1 << self.log));
+        self.push_boundary(l, r);
+
+        let (mut ll, mut rr) = (l, r);
+        while ll < rr {
+            if ll & 1 == 1 {
+                self.apply_node(ll, m.clone());
+                ll += 1;
+            }
+            if rr & 1 == 1 {
+                rr -= 1;
+                self.apply_node(rr, m.clone());
+            }
+            ll >>= 1;
+            rr >>= 1;
+        }
+
+        for i in 1..=self.log {
+            if (l >> i) << i != l {
+                self.update(l >> i);
+            }
+            if (r >> i) << i != r {
+                self.update((r - 1) >> i);
+            }
+        }
+    }
+
+    /// Push down the modifiers above the boundaries of leaves `l..r`
+    fn push_boundary(&mut self, l: usize, r: usize) {
+        for i in (1..=self.log).rev() {
+            if (l >> i) << i != l {
+                self.push(l >> i);
+            }
+            if (r >> i) << i != r {
+                self.push((r - 1) >> i);
+            }
+        }
+    }
+
+    fn update(&mut self, k: usize) {
+        self.values[k] = V::merge(self.values[2 * k].clone(), self.values[2 * k + 1].clone());
+    }
+
+    fn apply_node(&mut self, k: usize, m: M) {
+        let value = core::mem::replace(&mut self.values[k], V::empty());
+        self.values[k] = m.apply(value);
+        if k < self.modifiers.len() {
+            let modifier = core::mem::replace(&mut self.modifiers[k], M::empty());
+            self.modifiers[k] = M::merge(m, modifier);
+        }
+    }
+
+    fn push(&mut self, k: usize) {
+        let modifier = core::mem::replace(&mut self.modifiers[k], M::empty());
+        self.apply_node(2 * k, modifier.clone());
+        self.apply_node(2 * k + 1, modifier);
+    }
+}
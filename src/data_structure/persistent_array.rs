@@ -0,0 +1,65 @@
+use super::seg_tree::{
+    monoids::{NoOp, Single},
+    SegTree,
+};
+
+use alloc::vec::Vec;
+
+/// A persistent array with O(log n) `get` and `set` and O(1) `clone`
+///
+/// It is a [`SegTree`] of [`Single`] values that never merges anything
+/// useful, so there is no need to know about monoids to use it.
+#[derive(Debug, Clone)]
+pub struct PersistentArray<T> {
+    tree: SegTree<Single<T>, NoOp>,
+}
+
+impl<T> PersistentArray<T> {
+    pub fn len(&self) -> usize {
+        self.tree.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Clone> PersistentArray<T> {
+    /// The value at `index`, `None` if it is out of bounds
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.tree.get(index).0
+    }
+
+    /// Replace the value at `index`, returning the new array
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= self.len()`.
+    pub fn set(&self, index: usize, value: T) -> Self {
+        PersistentArray {
+            tree: self.tree.set(index, Single(Some(value))),
+        }
+    }
+
+    /// Iterate over the values from left to right
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.tree
+            .iter()
+            .map(|Single(v)| v.expect("every element holds a value"))
+    }
+}
+
+impl<T: Clone> FromIterator<T> for PersistentArray<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        PersistentArray {
+            tree: iter.into_iter().map(|v| Single(Some(v))).collect(),
+        }
+    }
+}
+
+impl<T: Clone> From<Vec<T>> for PersistentArray<T> {
+    #[inline]
+    fn from(values: Vec<T>) -> Self {
+        values.into_iter().collect()
+    }
+}
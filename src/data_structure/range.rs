@@ -0,0 +1,69 @@
+//! Turning the `RangeBounds` taken by every structure into `start..end`
+
+use core::fmt::Display;
+use core::ops::{Bound, Range, RangeBounds};
+
+/// The index types that ranges are given in
+pub(crate) trait Index: Copy + Ord + Display {
+    const ZERO: Self;
+    fn checked_succ(self) -> Option<Self>;
+}
+
+macro_rules! impl_index {
+    [$($t:ty),*] => {
+        $(
+        impl Index for $t {
+            const ZERO: Self = 0;
+            #[inline]
+            fn checked_succ(self) -> Option<Self> {
+                self.checked_add(1)
+            }
+        }
+        )*
+    };
+}
+
+impl_index![usize, u64];
+
+/// The half-open ends of `range` within `0..size`, `None` if one overflows
+fn bounds<T: Index>(range: &impl RangeBounds<T>, size: T) -> (Option<T>, Option<T>) {
+    let start = match range.start_bound() {
+        Bound::Included(&s) => Some(s),
+        Bound::Excluded(&s) => s.checked_succ(),
+        Bound::Unbounded => Some(T::ZERO),
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&e) => e.checked_succ(),
+        Bound::Excluded(&e) => Some(e),
+        Bound::Unbounded => Some(size),
+    };
+    (start, end)
+}
+
+/// Normalize `range` to a half-open `start..end` within `0..size`
+///
+/// # Panics
+///
+/// Panics if the range is reversed or ends beyond `size`, naming the
+/// structure as `what`, e.g. "range end 7 out of bounds for tree of size 6".
+pub(crate) fn resolve_range<T: Index>(range: impl RangeBounds<T>, size: T, what: &str) -> Range<T> {
+    let (start, end) = bounds(&range, size);
+    let start = start.expect("range start overflow");
+    let end = end.expect("range end overflow");
+    assert!(start <= end, "range {}..{} is invalid", start, end);
+    assert!(
+        end <= size,
+        "range end {} out of bounds for {} of size {}",
+        end,
+        what,
+        size
+    );
+    start..end
+}
+
+/// [`resolve_range`], with `None` where it would panic
+pub(crate) fn checked_range<T: Index>(range: impl RangeBounds<T>, size: T) -> Option<Range<T>> {
+    let (start, end) = bounds(&range, size);
+    let (start, end) = (start?, end?);
+    (start <= end && end <= size).then_some(start..end)
+}
@@ -0,0 +1,62 @@
+use super::{Applier, SegTree};
+use crate::traits::Monoid;
+
+use core::ops::{Range, RangeBounds};
+use std::collections::HashMap;
+
+/// A [`SegTree`] remembering the results of its queries
+///
+/// Every distinct range is queried on the tree once, repeats are answered
+/// from a map until the next [`CachedSegTree::apply`] drops all of them.
+/// This only pays off when the same ranges are queried again and again
+/// between updates; for update-heavy loads the bookkeeping makes it slower
+/// than querying the tree directly.
+#[derive(Debug, Clone)]
+pub struct CachedSegTree<V, M> {
+    tree: SegTree<V, M>,
+    cache: HashMap<Range<usize>, V>,
+}
+
+impl<V, M> CachedSegTree<V, M> {
+    pub fn new(tree: SegTree<V, M>) -> Self {
+        Self {
+            tree,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// The wrapped tree
+    pub fn tree(&self) -> &SegTree<V, M> {
+        &self.tree
+    }
+
+    pub fn into_inner(self) -> SegTree<V, M> {
+        self.tree
+    }
+}
+
+impl<V: Monoid + Clone, M: Applier<V> + Monoid + Clone> CachedSegTree<V, M> {
+    /// Same as [`SegTree::query`], from the cache if `range` was queried since the last update
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond the size of the tree.
+    pub fn query(&mut self, range: impl RangeBounds<usize>) -> V {
+        let range = self.tree.resolve_range(range);
+        let tree = &self.tree;
+        self.cache
+            .entry(range)
+            .or_insert_with_key(|range| tree.query(range.clone()))
+            .clone()
+    }
+
+    /// Apply `m` on `range`, forgetting every cached result
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond the size of the tree.
+    pub fn apply(&mut self, range: impl RangeBounds<usize>, m: M) {
+        self.tree = self.tree.apply(range, m);
+        self.cache.clear();
+    }
+}
@@ -0,0 +1,2344 @@
+use super::super::traits::*;
+
+#[cfg(feature = "std")]
+mod cached;
+pub mod monoids;
+mod one_indexed;
+mod versioned;
+
+pub use super::finger_tree::{ArcRef, RcRef};
+#[cfg(feature = "std")]
+pub use cached::CachedSegTree;
+pub use one_indexed::OneIndexed;
+pub use versioned::Versioned;
+
+use super::finger_tree::Ref;
+
+use alloc::{rc::Rc, sync::Arc, vec, vec::Vec};
+use core::{
+    cmp::{max, min},
+    ops::{Range, RangeBounds, Sub},
+};
+
+/// `m1.apply(a.merge(&b)) == m1.apply(a).merge(&m1.apply(b))`
+pub trait Applier<V: Semigroup> {
+    fn apply(&self, to: V) -> V;
+
+    /// Whether the values under a branch are to be read in the opposite
+    /// order once this is applied to it, which makes the branch swap its
+    /// children
+    ///
+    /// Only a [`Flip`](monoids::Flip) holding a reversal from
+    /// [`SegTree::reverse`] does, every other modifier keeps the default.
+    #[inline]
+    fn reverses(&self) -> bool {
+        false
+    }
+}
+
+/// A tuple of modifiers applies to a tuple of values component-wise
+macro_rules! impl_tuple_applier {
+    ($(($v:ident, $m:ident, $i:tt)),*) => {
+        impl<$($v: Semigroup, $m: Applier<$v>),*> Applier<($($v,)*)> for ($($m,)*) {
+            #[inline]
+            fn apply(&self, to: ($($v,)*)) -> ($($v,)*) {
+                ($(self.$i.apply(to.$i),)*)
+            }
+        }
+    };
+}
+
+impl_tuple_applier!((A, MA, 0), (B, MB, 1));
+impl_tuple_applier!((A, MA, 0), (B, MB, 1), (C, MC, 2));
+impl_tuple_applier!((A, MA, 0), (B, MB, 1), (C, MC, 2), (D, MD, 3));
+impl_tuple_applier!((A, MA, 0), (B, MB, 1), (C, MC, 2), (D, MD, 3), (E, ME, 4));
+
+impl<A: Semigroup, M: Applier<A>> Applier<A> for Option<M> {
+    fn apply(&self, to: A) -> A {
+        match self {
+            None => to,
+            Some(f) => f.apply(to),
+        }
+    }
+
+    fn reverses(&self) -> bool {
+        matches!(self, Some(f) if f.reverses())
+    }
+}
+
+/// Values carrying a non-negative count, searched by [`SegTree::kth`]
+pub trait Count {
+    type Count: PartialOrd + Copy + Sub<Output = Self::Count>;
+    fn count(&self) -> Self::Count;
+}
+
+/// Values that can be read backwards, see [`SegTree::reverse`]
+///
+/// `v.swap_sides()` is what `v` would be for its range read from right to
+/// left, e.g. a prefix becomes a suffix. It has to be an involution,
+/// `v.swap_sides().swap_sides() == v`, and reverse the order of merging,
+/// `a.merge(b).swap_sides() == b.swap_sides().merge(a.swap_sides())`.
+/// Commutative values are their own mirror image.
+pub trait Reversible: Semigroup {
+    fn swap_sides(self) -> Self;
+}
+
+/// The kind of pointer that a [`SegTree`] shares its subtrees with
+///
+/// [`RcRef`] is the default, [`ArcRef`] makes the tree `Send + Sync`
+/// (given that the values and modifiers are) at the cost of atomic
+/// reference counting.
+pub trait SharedRef {
+    type Ptr<T>: Ref<T>;
+}
+
+impl SharedRef for RcRef {
+    type Ptr<T> = Rc<T>;
+}
+
+impl SharedRef for ArcRef {
+    type Ptr<T> = Arc<T>;
+}
+
+/// Iterator over the values of a [`SegTree`], see [`SegTree::iter`]
+pub struct Iter<'a, V, M, R: SharedRef = RcRef> {
+    /// Nodes to visit, paired with the modifier pending from their ancestors
+    stack: Vec<(&'a SegTree<V, M, R>, M)>,
+}
+
+/// Owning iterator over the values of a [`SegTree`], see [`SegTree::into_iter`]
+pub struct IntoIter<V, M, R: SharedRef = RcRef> {
+    /// Nodes to visit, paired with the modifier pending from their ancestors
+    stack: Vec<(SegTree<V, M, R>, M)>,
+}
+
+/// A persistent segment tree
+///
+/// Cloning is cheap: only the root is copied, the subtrees are shared,
+/// through an `Rc` by default, see [`SharedRef`].
+pub enum SegTree<V, M, R: SharedRef = RcRef> {
+    Empty,
+    Unit(V),
+    Branch {
+        size: usize,
+        modifier: M,
+        value: V,
+        left: R::Ptr<SegTree<V, M, R>>,
+        right: R::Ptr<SegTree<V, M, R>>,
+    },
+}
+
+impl<V: Clone, M: Clone, R: SharedRef> SegTree<V, M, R> {
+    /// Take the node out of `ptr` if nothing else refers to it, clone it otherwise
+    #[inline]
+    fn unshare(ptr: R::Ptr<Self>) -> Self {
+        Ref::try_unwrap(ptr).unwrap_or_else(|shared| (*shared).clone())
+    }
+}
+
+impl<V: Clone, M: Clone, R: SharedRef> Clone for SegTree<V, M, R> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Empty => Self::Empty,
+            Self::Unit(v) => Self::Unit(v.clone()),
+            Self::Branch {
+                size,
+                modifier,
+                value,
+                left,
+                right,
+            } => Self::Branch {
+                size: *size,
+                modifier: modifier.clone(),
+                value: value.clone(),
+                left: left.clone(),
+                right: right.clone(),
+            },
+        }
+    }
+}
+
+impl<V: core::fmt::Debug, M: core::fmt::Debug, R: SharedRef> core::fmt::Debug for SegTree<V, M, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Empty => f.write_str("Empty"),
+            Self::Unit(v) => f.debug_tuple("Unit").field(v).finish(),
+            Self::Branch {
+                size,
+                modifier,
+                value,
+                left,
+                right,
+            } => f
+                .debug_struct("Branch")
+                .field("size", size)
+                .field("modifier", modifier)
+                .field("value", value)
+                .field("left", &**left)
+                .field("right", &**right)
+                .finish(),
+        }
+    }
+}
+
+/// The tree of size 0
+impl<V, M, R: SharedRef> Default for SegTree<V, M, R> {
+    fn default() -> Self {
+        Self::Empty
+    }
+}
+
+/// A [`SegTree`] sharing its subtrees through an `Arc`, so it can be sent to other threads
+pub type ArcSegTree<V, M> = SegTree<V, M, ArcRef>;
+
+impl<V, M, R: SharedRef> SegTree<V, M, R> {
+    #[inline]
+    fn share(node: Self) -> R::Ptr<Self> {
+        Ref::new(node)
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Empty => 0,
+            Self::Unit(_) => 1,
+            Self::Branch { size, .. } => *size,
+        }
+    }
+
+    /// The number of values, the same as [`Self::size`]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.size()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Self::Empty)
+    }
+
+    /// Count the `Unit`s and `Branch`es in this tree
+    ///
+    /// A subtree shared by several paths is counted once per path, so this is
+    /// the size of the tree as if nothing were shared, i.e. `2 * size - 1`
+    /// for a non-empty tree. See [`Self::shared_node_count`] for the memory
+    /// actually used.
+    pub fn node_count(&self) -> usize {
+        match self {
+            Self::Empty => 0,
+            Self::Unit(_) => 1,
+            Self::Branch { left, right, .. } => 1 + left.node_count() + right.node_count(),
+        }
+    }
+
+    /// Count the distinct nodes reachable from any of `roots`
+    ///
+    /// Subtrees are identified by the address of their `Rc`, so those shared
+    /// between versions are counted once. Each root itself counts as a node.
+    pub fn shared_node_count(roots: &[&Self]) -> usize {
+        let mut seen = alloc::collections::BTreeSet::new();
+        let mut stack: Vec<&Self> = Vec::new();
+        let mut count = 0;
+        for &root in roots {
+            if !matches!(root, Self::Empty) {
+                count += 1;
+                stack.push(root);
+            }
+            while let Some(node) = stack.pop() {
+                if let Self::Branch { left, right, .. } = node {
+                    for child in [left, right] {
+                        if seen.insert(&**child as *const Self) {
+                            count += 1;
+                            stack.push(child);
+                        }
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    /// Normalize `range` to a half-open `start..end` within this tree
+    fn resolve_range(&self, range: impl RangeBounds<usize>) -> Range<usize> {
+        super::range::resolve_range(range, self.size(), "tree")
+    }
+
+    /// [`Self::resolve_range`], with `None` where it would panic
+    fn checked_range(&self, range: impl RangeBounds<usize>) -> Option<Range<usize>> {
+        super::range::checked_range(range, self.size())
+    }
+}
+
+impl<V: Monoid + Clone, M, R: SharedRef> SegTree<V, M, R> {
+    /// The merged value of the whole tree in O(1),
+    /// the same as `self.query(..)`
+    #[inline]
+    pub fn total(&self) -> V {
+        self.all()
+    }
+
+    fn all(&self) -> V {
+        match self {
+            Self::Empty => V::empty(),
+            Self::Unit(v) => v.clone(),
+            Self::Branch { value, .. } => value.clone(),
+        }
+    }
+}
+
+impl<V: Clone + Semigroup, M: Clone + Semigroup + Applier<V>, R: SharedRef> SegTree<V, M, R> {
+    fn apply_all(&self, m: M) -> Self {
+        match self {
+            Self::Empty => Self::Empty,
+            Self::Unit(v) => Self::Unit(m.apply(v.clone())),
+            Self::Branch {
+                size,
+                modifier,
+                value,
+                left,
+                right,
+            } => {
+                let (left, right) = Self::in_order(&m, left, right);
+                Self::Branch {
+                    size: *size,
+                    value: m.apply(value.clone()),
+                    modifier: M::merge(m, modifier.clone()),
+                    left: left.clone(),
+                    right: right.clone(),
+                }
+            }
+        }
+    }
+
+    /// The children of a branch in the order they are read in once `acc`,
+    /// pending from its ancestors, is applied to it
+    ///
+    /// A branch has its children swapped when a reversal is applied to it,
+    /// so the ones below a pending reversal are still in the old order.
+    #[inline]
+    fn in_order<T>(acc: &M, left: T, right: T) -> (T, T) {
+        if acc.reverses() {
+            (right, left)
+        } else {
+            (left, right)
+        }
+    }
+}
+
+impl<V: Monoid + Clone, M: Monoid, R: SharedRef> SegTree<V, M, R> {
+    /// A branch without pending modifier over two non-empty trees
+    fn node(left: Self, right: Self) -> Self {
+        Self::Branch {
+            size: left.size() + right.size(),
+            modifier: M::empty(),
+            value: V::merge(left.all(), right.all()),
+            left: Self::share(left),
+            right: Self::share(right),
+        }
+    }
+
+    /// [`Self::node`] over two shared non-empty trees
+    fn branch(left: R::Ptr<Self>, right: R::Ptr<Self>) -> Self {
+        Self::Branch {
+            size: left.size() + right.size(),
+            modifier: M::empty(),
+            value: V::merge(left.all(), right.all()),
+            left,
+            right,
+        }
+    }
+
+    /// Build bottom-up by pairing the nodes level by level,
+    /// so the depth is `ceil(log2(n))` without any recursion.
+    fn from_values<I: IntoIterator<Item = V>>(values: I) -> Self {
+        Self::pair_up(values, |left, right, _| Self::node(left, right))
+    }
+
+    /// The bottom-up build of [`Self::from_values`], with `join` making each
+    /// branch out of its children and the range it covers
+    fn pair_up<I, F>(values: I, mut join: F) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        F: FnMut(Self, Self, Range<usize>) -> Self,
+    {
+        let mut level: Vec<Self> = values.into_iter().map(Self::Unit).collect();
+        while level.len() > 1 {
+            let mut next = Vec::with_capacity(level.len().div_ceil(2));
+            let mut nodes = level.into_iter();
+            let mut start = 0;
+            while let Some(left) = nodes.next() {
+                let node = match nodes.next() {
+                    Some(right) => {
+                        let end = start + left.size() + right.size();
+                        join(left, right, start..end)
+                    }
+                    None => left,
+                };
+                start += node.size();
+                next.push(node);
+            }
+            level = next;
+        }
+        level.pop().unwrap_or(Self::Empty)
+    }
+}
+
+impl<V: Monoid + Clone, M: Applier<V> + Monoid + Clone, R: SharedRef> SegTree<V, M, R> {
+    /// Build a SegTree whose value at `i` is `init(i)`
+    pub fn build<F: Fn(usize) -> V>(len: usize, init: F) -> Self {
+        Self::from_values((0..len).map(init))
+    }
+
+    /// Build a SegTree whose value at `i` is `init(i)`, unless one of them fails
+    ///
+    /// `init` is called for the indices in order, and not any more after the
+    /// first `Err`, which is returned.
+    pub fn try_build<E, F: Fn(usize) -> Result<V, E>>(len: usize, init: F) -> Result<Self, E> {
+        let values = (0..len).map(init).collect::<Result<Vec<V>, E>>()?;
+        Ok(Self::from_values(values))
+    }
+
+    /// Build a SegTree of `len` values that are all `V::empty()`, to be filled in later
+    pub fn zeros(len: usize) -> Self {
+        Self::build(len, |_| V::empty())
+    }
+
+    /// Build a SegTree of the first `len` items of `iter`
+    ///
+    /// Items are consumed exactly once, in index order, so `iter` may read
+    /// them off the input as it goes. Items after the first `len` are not
+    /// touched, pass `&mut iter` to keep using them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `iter` has fewer than `len` items.
+    pub fn build_from_iter<I: IntoIterator<Item = V>>(len: usize, iter: I) -> Self {
+        let mut iter = iter.into_iter();
+        Self::from_values((0..len).map(|i| {
+            iter.next()
+                .unwrap_or_else(|| panic!("iterator ended after {} of {} items", i, len))
+        }))
+    }
+
+    /// Build a SegTree whose value at `i` is `init_value(i)`, with each branch
+    /// holding `init_mod(range)` as its pending modifier, where `range` is
+    /// the part of the tree that it covers
+    ///
+    /// This restores a tree in the middle of lazy updates as if `init_mod(r)`
+    /// had been applied on `r` to the branch covering `r`, from the leaves
+    /// upwards. So the value at `i` ends up being `init_value(i)` with the
+    /// modifiers of all the ranges containing `i` applied, the innermost
+    /// first. Any `init_mod` is fine as long as `M` satisfies the laws of
+    /// [`Applier`], but which ranges are asked for is an implementation
+    /// detail of the shape, the same one as [`Self::build`].
+    pub fn build_with<F, G>(len: usize, init_value: F, init_mod: G) -> Self
+    where
+        F: Fn(usize) -> V,
+        G: Fn(Range<usize>) -> M,
+    {
+        Self::pair_up((0..len).map(init_value), |left, right, range| {
+            let modifier = init_mod(range);
+            Self::Branch {
+                size: left.size() + right.size(),
+                value: modifier.apply(V::merge(left.all(), right.all())),
+                modifier,
+                left: Self::share(left),
+                right: Self::share(right),
+            }
+        })
+    }
+
+    /// Query the merged value of a SegTree range
+    ///
+    /// The values are merged in index order, the left operand of every
+    /// merge covering lower indices than the right one, so non-commutative
+    /// values like [`monoids::Matrix`] or strings come out left to right.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The range to query, e.g. `start..end`, `..=end` or `..`
+    ///   for the whole tree
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn query(&self, range: impl RangeBounds<usize>) -> V {
+        let range = self.resolve_range(range);
+        if range.is_empty() {
+            return V::empty();
+        }
+        self.query_inner(range)
+    }
+
+    /// Query `l..=r` with both ends included, as problem statements put it
+    ///
+    /// The same as `query(l..=r)`, `r == self.size() - 1` reaches the end.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `r >= self.size()`, or `l > r + 1`.
+    pub fn query_inclusive(&self, l: usize, r: usize) -> V {
+        self.query(l..=r)
+    }
+
+    /// [`SegTree::query`], or `None` instead of panicking if the range is
+    /// reversed or ends beyond `self.size()`
+    pub fn query_checked(&self, range: impl RangeBounds<usize>) -> Option<V> {
+        self.checked_range(range).map(|range| self.query(range))
+    }
+
+    /// The merged value of the first `n` elements, same as `query(..n)`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n > self.size()`.
+    pub fn query_prefix(&self, n: usize) -> V {
+        let range = self.resolve_range(..n);
+        self.query_prefix_with(range.end, M::empty())
+    }
+
+    /// The merged value from index `n` to the end, same as `query(n..)`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n > self.size()`.
+    pub fn query_suffix(&self, n: usize) -> V {
+        let range = self.resolve_range(n..);
+        self.query_suffix_with(range.start, M::empty())
+    }
+
+    fn query_inner(&self, range: Range<usize>) -> V {
+        // walk down while `range` fits in one child, carrying the pending
+        // modifiers of the ancestors in `acc`
+        let mut node = self;
+        let mut range = range;
+        let mut acc = M::empty();
+        let (left, right, mid) = loop {
+            match node {
+                Self::Empty => return V::empty(),
+                Self::Unit(v) => {
+                    return if range.contains(&0) {
+                        acc.apply(v.clone())
+                    } else {
+                        V::empty()
+                    }
+                }
+                Self::Branch {
+                    size,
+                    modifier,
+                    value,
+                    left,
+                    right,
+                } => {
+                    if range.start == 0 && *size <= range.end {
+                        return acc.apply(value.clone());
+                    }
+                    let (left, right) = Self::in_order(&acc, left, right);
+                    let mid = left.size();
+                    acc = M::merge(acc, modifier.clone());
+                    if range.end <= mid {
+                        node = left;
+                    } else if mid <= range.start {
+                        range = range.start - mid..range.end - mid;
+                        node = right;
+                    } else {
+                        break (&**left, &**right, mid);
+                    }
+                }
+            }
+        };
+
+        V::merge(
+            left.query_suffix_with(range.start, acc.clone()),
+            right.query_prefix_with(range.end - mid, acc),
+        )
+    }
+
+    /// `acc.apply(self.query(start..))` without recursion
+    fn query_suffix_with(&self, mut start: usize, mut acc: M) -> V {
+        // pieces found deeper lie to the left of the ones found so far
+        let mut node = self;
+        let mut result = V::empty();
+        loop {
+            match node {
+                Self::Branch {
+                    modifier,
+                    left,
+                    right,
+                    ..
+                } if start > 0 => {
+                    let (left, right) = Self::in_order(&acc, left, right);
+                    let mid = left.size();
+                    acc = M::merge(acc, modifier.clone());
+                    if start < mid {
+                        result = V::merge(acc.apply(right.all()), result);
+                        node = left;
+                    } else {
+                        start -= mid;
+                        node = right;
+                    }
+                }
+                Self::Empty => return result,
+                Self::Unit(_) if start > 0 => return result,
+                _ => return V::merge(acc.apply(node.all()), result),
+            }
+        }
+    }
+
+    /// `acc.apply(self.query(..end))` without recursion
+    fn query_prefix_with(&self, mut end: usize, mut acc: M) -> V {
+        // pieces found deeper lie to the right of the ones found so far
+        let mut node = self;
+        let mut result = V::empty();
+        loop {
+            match node {
+                Self::Branch {
+                    size,
+                    modifier,
+                    left,
+                    right,
+                    ..
+                } if end < *size => {
+                    let (left, right) = Self::in_order(&acc, left, right);
+                    let mid = left.size();
+                    acc = M::merge(acc, modifier.clone());
+                    if mid < end {
+                        result = V::merge(result, acc.apply(left.all()));
+                        end -= mid;
+                        node = right;
+                    } else {
+                        node = left;
+                    }
+                }
+                Self::Empty => return result,
+                Self::Unit(_) if end == 0 => return result,
+                _ => return V::merge(result, acc.apply(node.all())),
+            }
+        }
+    }
+
+    /// Fold over the nodes covering `range` from left to right
+    ///
+    /// These are the same nodes whose values [`Self::query`] merges: the
+    /// maximal branches inside `range` are passed to `branch_fn` and the
+    /// leaves that are not under such a branch to `leaf_fn`, both with
+    /// their pending modifiers applied. There are O(log n) of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn fold_range<A, F, G>(
+        &self,
+        range: impl RangeBounds<usize>,
+        init: A,
+        mut branch_fn: F,
+        mut leaf_fn: G,
+    ) -> A
+    where
+        F: FnMut(A, &V) -> A,
+        G: FnMut(A, &V) -> A,
+    {
+        let range = self.resolve_range(range);
+        self.fold_range_inner(range, M::empty(), init, &mut branch_fn, &mut leaf_fn)
+    }
+
+    fn fold_range_inner<A, F, G>(
+        &self,
+        range: Range<usize>,
+        acc: M,
+        init: A,
+        branch_fn: &mut F,
+        leaf_fn: &mut G,
+    ) -> A
+    where
+        F: FnMut(A, &V) -> A,
+        G: FnMut(A, &V) -> A,
+    {
+        match self {
+            Self::Empty => init,
+            Self::Unit(v) => {
+                if range.contains(&0) {
+                    leaf_fn(init, &acc.apply(v.clone()))
+                } else {
+                    init
+                }
+            }
+            Self::Branch {
+                size,
+                modifier,
+                value,
+                left,
+                right,
+            } => {
+                if range.start == 0 && *size <= range.end {
+                    return branch_fn(init, &acc.apply(value.clone()));
+                }
+                let (left, right) = Self::in_order(&acc, left, right);
+                let mid = left.size();
+                let acc = M::merge(acc, modifier.clone());
+                let init = if range.start < mid {
+                    left.fold_range_inner(
+                        range.start..min(range.end, mid),
+                        acc.clone(),
+                        init,
+                        branch_fn,
+                        leaf_fn,
+                    )
+                } else {
+                    init
+                };
+                if mid < range.end {
+                    right.fold_range_inner(
+                        max(range.start, mid) - mid..range.end - mid,
+                        acc,
+                        init,
+                        branch_fn,
+                        leaf_fn,
+                    )
+                } else {
+                    init
+                }
+            }
+        }
+    }
+
+    /// The values of the O(log n) maximal nodes covering `range`, from left to right
+    ///
+    /// Merging them gives `self.query(range)`, see [`Self::fold_range`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn decompose(&self, range: impl RangeBounds<usize>) -> Vec<V> {
+        let push = |mut pieces: Vec<V>, v: &V| {
+            pieces.push(v.clone());
+            pieces
+        };
+        self.fold_range(range, Vec::new(), push, push)
+    }
+
+    /// Count the elements in `range` satisfying `pred`
+    ///
+    /// `pred` sees each element with its pending modifiers applied. Every
+    /// element of the range is visited, nothing is pruned by the aggregates,
+    /// so this takes O(r - l + log n).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn count_if<P: Fn(&V) -> bool>(&self, range: impl RangeBounds<usize>, pred: P) -> usize {
+        let range = self.resolve_range(range);
+        self.count_if_inner(range, M::empty(), &pred)
+    }
+
+    fn count_if_inner<P: Fn(&V) -> bool>(&self, range: Range<usize>, acc: M, pred: &P) -> usize {
+        match self {
+            Self::Empty => 0,
+            Self::Unit(v) => (range.contains(&0) && pred(&acc.apply(v.clone()))) as usize,
+            Self::Branch {
+                modifier,
+                left,
+                right,
+                ..
+            } => {
+                if range.is_empty() {
+                    return 0;
+                }
+                let (left, right) = Self::in_order(&acc, left, right);
+                let mid = left.size();
+                let acc = M::merge(acc, modifier.clone());
+                let mut count = 0;
+                if range.start < mid {
+                    count +=
+                        left.count_if_inner(range.start..min(range.end, mid), acc.clone(), pred);
+                }
+                if mid < range.end {
+                    count += right.count_if_inner(
+                        max(range.start, mid) - mid..range.end - mid,
+                        acc,
+                        pred,
+                    );
+                }
+                count
+            }
+        }
+    }
+
+    /// Rebuild the same values into the shape of [`Self::build`] in O(n)
+    ///
+    /// The depth goes back to `⌈log2 n⌉` whatever the edits before, and no
+    /// modifiers are left pending. All the nodes are new, so nothing is
+    /// shared with `self` or the other versions any more.
+    pub fn rebuild(&self) -> Self {
+        Self::from_values(self.iter())
+    }
+
+    /// The same tree with every pending modifier pushed down into the leaves
+    ///
+    /// The shape is kept, every branch of the result holds `M::empty()` and
+    /// a `value` that is just its children merged, so the nodes can be
+    /// inspected field by field. This rebuilds all the nodes in O(n).
+    pub fn normalized(&self) -> Self {
+        self.normalized_inner(M::empty())
+    }
+
+    fn normalized_inner(&self, acc: M) -> Self {
+        match self {
+            Self::Empty => Self::Empty,
+            Self::Unit(v) => Self::Unit(acc.apply(v.clone())),
+            Self::Branch {
+                modifier,
+                left,
+                right,
+                ..
+            } => {
+                let (left, right) = Self::in_order(&acc, left, right);
+                let acc = M::merge(acc, modifier.clone());
+                Self::node(
+                    left.normalized_inner(acc.clone()),
+                    right.normalized_inner(acc),
+                )
+            }
+        }
+    }
+
+    /// Collect all values in order, with all pending modifiers applied
+    pub fn to_vec(&self) -> Vec<V> {
+        self.iter().collect()
+    }
+
+    /// All the prefix aggregates `[query(..1), query(..2), ..., query(..n)]`
+    ///
+    /// Folds the values in one pass from left to right in O(n), instead of
+    /// the O(n log n) of querying each prefix.
+    pub fn prefix_scan(&self) -> Vec<V> {
+        self.iter()
+            .scan(V::empty(), |acc, v| {
+                *acc = V::merge(core::mem::replace(acc, V::empty()), v);
+                Some(acc.clone())
+            })
+            .collect()
+    }
+
+    /// Iterate over the values from left to right, with all pending modifiers applied
+    pub fn iter(&self) -> Iter<'_, V, M, R> {
+        Iter {
+            stack: vec![(self, M::empty())],
+        }
+    }
+
+    /// Iterate over every node, branches and leaves, with the range it covers
+    ///
+    /// The nodes come in pre-order: a branch, then all of its left subtree,
+    /// then all of its right subtree, so the root comes first and the leaves
+    /// appear left to right. Each value has the pending modifiers of its
+    /// ancestors applied, i.e. it is what `query` of its range returns.
+    pub fn nodes(&self) -> impl Iterator<Item = (Range<usize>, V)> + '_ {
+        let mut stack = vec![(self, 0, M::empty())];
+        core::iter::from_fn(move || {
+            while let Some((node, offset, acc)) = stack.pop() {
+                match node {
+                    Self::Empty => {}
+                    Self::Unit(v) => return Some((offset..offset + 1, acc.apply(v.clone()))),
+                    Self::Branch {
+                        size,
+                        modifier,
+                        value,
+                        left,
+                        right,
+                    } => {
+                        let value = acc.apply(value.clone());
+                        let (left, right) = Self::in_order(&acc, left, right);
+                        let acc = M::merge(acc, modifier.clone());
+                        stack.push((right, offset + left.size(), acc.clone()));
+                        stack.push((left, offset, acc));
+                        return Some((offset..offset + size, value));
+                    }
+                }
+            }
+            None
+        })
+    }
+
+    /// A new tree of the same shape whose value at `i` is `f(self.get(i))`
+    ///
+    /// `f` sees the values with all pending modifiers applied, and the new
+    /// tree starts with no pending modifier.
+    pub fn map<W, N, F>(&self, f: F) -> SegTree<W, N, R>
+    where
+        W: Monoid + Clone,
+        N: Monoid,
+        F: Fn(V) -> W,
+    {
+        self.map_inner(M::empty(), &f)
+    }
+
+    fn map_inner<W, N, F>(&self, acc: M, f: &F) -> SegTree<W, N, R>
+    where
+        W: Monoid + Clone,
+        N: Monoid,
+        F: Fn(V) -> W,
+    {
+        match self {
+            Self::Empty => SegTree::Empty,
+            Self::Unit(v) => SegTree::Unit(f(acc.apply(v.clone()))),
+            Self::Branch {
+                modifier,
+                left,
+                right,
+                ..
+            } => {
+                let (left, right) = Self::in_order(&acc, left, right);
+                let acc = M::merge(acc, modifier.clone());
+                SegTree::node(left.map_inner(acc.clone(), f), right.map_inner(acc, f))
+            }
+        }
+    }
+
+    /// A new tree whose value at `i` is `f(self.get(i), other.get(i))`
+    ///
+    /// Both sides are resolved with their pending modifiers before `f` sees
+    /// them, and the result is built from scratch in O(n).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sizes differ.
+    pub fn zip_with<W, N, F>(&self, other: &Self, f: F) -> SegTree<W, N, R>
+    where
+        W: Monoid + Clone,
+        N: Monoid,
+        F: Fn(V, V) -> W,
+    {
+        assert_eq!(
+            self.size(),
+            other.size(),
+            "cannot zip trees of different sizes"
+        );
+        SegTree::from_values(self.iter().zip(other.iter()).map(|(a, b)| f(a, b)))
+    }
+
+    /// Find the smallest `i` that the count of `query(..=i)` reaches `k`
+    ///
+    /// Counts must be non-negative, so that the running count never
+    /// decreases. Returns `None` if even the whole tree counts less than
+    /// `k`, and `Some(0)` for a non-positive `k` on a non-empty tree.
+    /// Runs in O(log n) by comparing `k` with the left child's count.
+    pub fn kth(&self, k: V::Count) -> Option<usize>
+    where
+        V: Count,
+    {
+        if self.all().count() < k {
+            return None;
+        }
+        let mut node = self;
+        let (mut k, mut offset, mut acc) = (k, 0, M::empty());
+        loop {
+            match node {
+                Self::Empty => return None,
+                Self::Unit(_) => return Some(offset),
+                Self::Branch {
+                    modifier,
+                    left,
+                    right,
+                    ..
+                } => {
+                    let (left, right) = Self::in_order(&acc, left, right);
+                    acc = M::merge(acc, modifier.clone());
+                    let count = acc.apply(left.all()).count();
+                    if k <= count {
+                        node = left;
+                    } else {
+                        k = k - count;
+                        offset += left.size();
+                        node = right;
+                    }
+                }
+            }
+        }
+    }
+
+    /// [`SegTree::kth`] on the difference of two versions of a count tree
+    ///
+    /// Finds the smallest `i` that the count of `newer.query(..=i)` minus the
+    /// count of `older.query(..=i)` reaches `k`, e.g. the `k`-th smallest value
+    /// of a subarray from the count trees of two prefixes of a compressed array.
+    /// The difference has to be non-negative everywhere, as when `newer` only
+    /// adds counts on top of `older`. Runs in O(log n) by walking both trees
+    /// in lockstep.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the trees are not shaped alike, as versions created by
+    /// [`SegTree::apply`] from the same tree are.
+    pub fn kth_in_difference(older: &Self, newer: &Self, k: V::Count) -> Option<usize>
+    where
+        V: Count,
+    {
+        assert_eq!(
+            older.size(),
+            newer.size(),
+            "cannot compare trees of different sizes"
+        );
+        if newer.all().count() - older.all().count() < k {
+            return None;
+        }
+        let (mut old, mut new) = (older, newer);
+        let (mut k, mut offset) = (k, 0);
+        let (mut old_acc, mut new_acc) = (M::empty(), M::empty());
+        loop {
+            match (old, new) {
+                (Self::Empty, Self::Empty) => return None,
+                (Self::Unit(_), Self::Unit(_)) => return Some(offset),
+                (
+                    Self::Branch {
+                        modifier: old_modifier,
+                        left: old_left,
+                        right: old_right,
+                        ..
+                    },
+                    Self::Branch {
+                        modifier: new_modifier,
+                        left: new_left,
+                        right: new_right,
+                        ..
+                    },
+                ) => {
+                    let (old_left, old_right) = Self::in_order(&old_acc, old_left, old_right);
+                    let (new_left, new_right) = Self::in_order(&new_acc, new_left, new_right);
+                    assert_eq!(
+                        old_left.size(),
+                        new_left.size(),
+                        "trees are shaped differently"
+                    );
+                    old_acc = M::merge(old_acc, old_modifier.clone());
+                    new_acc = M::merge(new_acc, new_modifier.clone());
+                    let count = new_acc.apply(new_left.all()).count()
+                        - old_acc.apply(old_left.all()).count();
+                    if k <= count {
+                        (old, new) = (old_left, new_left);
+                    } else {
+                        k = k - count;
+                        offset += new_left.size();
+                        (old, new) = (old_right, new_right);
+                    }
+                }
+                _ => panic!("trees are shaped differently"),
+            }
+        }
+    }
+
+    /// Find the largest `r` that `pred(&self.query(l..r))` holds
+    ///
+    /// `pred` must be monotone, i.e. once it fails on `query(l..r)`,
+    /// it also fails on every `query(l..r')` with `r < r'`,
+    /// and `pred(&V::empty())` must be `true`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `l > self.size()`.
+    pub fn max_right<P: Fn(&V) -> bool>(&self, l: usize, pred: P) -> usize {
+        assert!(
+            l <= self.size(),
+            "index {} out of bounds for tree of size {}",
+            l,
+            self.size()
+        );
+        let mut sum = V::empty();
+        self.max_right_inner(l, M::empty(), &mut sum, &pred)
+            .unwrap_or(self.size())
+    }
+
+    /// Returns the position where `pred` first fails, merging everything before it into `sum`
+    fn max_right_inner<P: Fn(&V) -> bool>(
+        &self,
+        l: usize,
+        acc: M,
+        sum: &mut V,
+        pred: &P,
+    ) -> Option<usize> {
+        match self {
+            Self::Empty => None,
+            Self::Unit(v) => {
+                if l > 0 {
+                    return None;
+                }
+                let merged = V::merge(sum.clone(), acc.apply(v.clone()));
+                if pred(&merged) {
+                    *sum = merged;
+                    None
+                } else {
+                    Some(0)
+                }
+            }
+            Self::Branch {
+                size,
+                modifier,
+                value,
+                left,
+                right,
+            } => {
+                if l == 0 {
+                    let merged = V::merge(sum.clone(), acc.apply(value.clone()));
+                    if pred(&merged) {
+                        *sum = merged;
+                        return None;
+                    }
+                } else if *size <= l {
+                    return None;
+                }
+
+                let (left, right) = Self::in_order(&acc, left, right);
+                let mid = left.size();
+                let acc = M::merge(acc, modifier.clone());
+                if l < mid {
+                    if let Some(r) = left.max_right_inner(l, acc.clone(), sum, pred) {
+                        return Some(r);
+                    }
+                }
+                right
+                    .max_right_inner(l.saturating_sub(mid), acc, sum, pred)
+                    .map(|r| r + mid)
+            }
+        }
+    }
+
+    /// Find the smallest `l` that `pred(&self.query(l..r))` holds
+    ///
+    /// `pred` must be monotone, i.e. once it fails on `query(l..r)`,
+    /// it also fails on every `query(l'..r)` with `l' < l`,
+    /// and `pred(&V::empty())` must be `true`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `r > self.size()`.
+    pub fn min_left<P: Fn(&V) -> bool>(&self, r: usize, pred: P) -> usize {
+        assert!(
+            r <= self.size(),
+            "index {} out of bounds for tree of size {}",
+            r,
+            self.size()
+        );
+        let mut sum = V::empty();
+        self.min_left_inner(r, M::empty(), &mut sum, &pred)
+            .unwrap_or(0)
+    }
+
+    /// Returns the position after where `pred` first fails, merging everything after it into `sum`
+    fn min_left_inner<P: Fn(&V) -> bool>(
+        &self,
+        r: usize,
+        acc: M,
+        sum: &mut V,
+        pred: &P,
+    ) -> Option<usize> {
+        match self {
+            Self::Empty => None,
+            Self::Unit(v) => {
+                if r == 0 {
+                    return None;
+                }
+                // the new segment lies on the left of `sum`
+                let merged = V::merge(acc.apply(v.clone()), sum.clone());
+                if pred(&merged) {
+                    *sum = merged;
+                    None
+                } else {
+                    Some(1)
+                }
+            }
+            Self::Branch {
+                size,
+                modifier,
+                value,
+                left,
+                right,
+            } => {
+                if *size <= r {
+                    let merged = V::merge(acc.apply(value.clone()), sum.clone());
+                    if pred(&merged) {
+                        *sum = merged;
+                        return None;
+                    }
+                } else if r == 0 {
+                    return None;
+                }
+
+                let (left, right) = Self::in_order(&acc, left, right);
+                let mid = left.size();
+                let acc = M::merge(acc, modifier.clone());
+                if mid < r {
+                    if let Some(l) = right.min_left_inner(r - mid, acc.clone(), sum, pred) {
+                        return Some(l + mid);
+                    }
+                }
+                left.min_left_inner(min(r, mid), acc, sum, pred)
+            }
+        }
+    }
+
+    /// The first index in `range` whose value is at least `x`
+    ///
+    /// Meant for values that merge into the larger one, like [`monoids::Max`],
+    /// where it descends only into the subtrees whose maximum reaches `x`,
+    /// in O(log n).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn find_first_ge(&self, range: impl RangeBounds<usize>, x: V) -> Option<usize>
+    where
+        V: Ord,
+    {
+        let range = self.resolve_range(range);
+        if x <= V::empty() {
+            // every value is at least the identity
+            return (!range.is_empty()).then_some(range.start);
+        }
+        let i = self.max_right(range.start, |v| *v < x);
+        (i < range.end).then_some(i)
+    }
+
+    /// The leftmost index in `range` holding the maximum of `range`, with that maximum
+    ///
+    /// Meant for values that merge into the larger one, like [`monoids::Max`]:
+    /// it descends along [`Self::max_right`] to the first position where the
+    /// running maximum reaches the one of the range, in O(log n). Returns
+    /// `None` if the range is empty or holds nothing but `V::empty()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn argmax(&self, range: impl RangeBounds<usize>) -> Option<(usize, V)>
+    where
+        V: Ord,
+    {
+        let range = self.resolve_range(range);
+        let best = self.query(range.clone());
+        if best == V::empty() {
+            return None;
+        }
+        Some((self.max_right(range.start, |v| *v < best), best))
+    }
+
+    /// The leftmost index in `range` holding the minimum of `range`, with that minimum
+    ///
+    /// The same as [`Self::argmax`] for values that merge into the smaller
+    /// one, like [`monoids::Min`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn argmin(&self, range: impl RangeBounds<usize>) -> Option<(usize, V)>
+    where
+        V: Ord,
+    {
+        let range = self.resolve_range(range);
+        let best = self.query(range.clone());
+        if best == V::empty() {
+            return None;
+        }
+        Some((self.max_right(range.start, |v| *v > best), best))
+    }
+
+    /// Get the value at `index` with all pending modifiers applied
+    ///
+    /// Returns `V::empty()` if `index >= self.size()`.
+    pub fn get(&self, index: usize) -> V {
+        match self {
+            Self::Empty => V::empty(),
+            Self::Unit(v) => {
+                if index == 0 {
+                    v.clone()
+                } else {
+                    V::empty()
+                }
+            }
+            Self::Branch {
+                size,
+                modifier,
+                left,
+                right,
+                ..
+            } => {
+                if *size <= index {
+                    return V::empty();
+                }
+                let mid = left.size();
+                let (child, index) = if index < mid {
+                    (left, index)
+                } else {
+                    (right, index - mid)
+                };
+                // below a pending reversal the child is still in the old order
+                let index = if modifier.reverses() {
+                    child.size() - 1 - index
+                } else {
+                    index
+                };
+                modifier.apply(child.get(index))
+            }
+        }
+    }
+
+    /// Replace the value at `index`, returning the new SegTree
+    ///
+    /// Pending modifiers on the path are pushed down first,
+    /// so the new value will not be modified by them later.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= self.size()`.
+    pub fn set(&self, index: usize, value: V) -> Self {
+        assert!(
+            index < self.size(),
+            "index {} out of bounds for tree of size {}",
+            index,
+            self.size()
+        );
+        self.set_inner(index, value)
+    }
+
+    fn set_inner(&self, index: usize, value: V) -> Self {
+        match self {
+            Self::Empty => Self::Empty,
+            Self::Unit(_) => Self::Unit(value),
+            Self::Branch {
+                size,
+                modifier,
+                left,
+                right,
+                ..
+            } => {
+                let mid = left.size();
+                let (new_left, new_right) = if index < mid {
+                    (
+                        Self::push_down_with(modifier, left, |l| l.set_inner(index, value)),
+                        Self::push_down(modifier, right),
+                    )
+                } else {
+                    (
+                        Self::push_down(modifier, left),
+                        Self::push_down_with(modifier, right, |r| r.set_inner(index - mid, value)),
+                    )
+                };
+
+                Self::Branch {
+                    size: *size,
+                    modifier: M::empty(),
+                    value: V::merge(new_left.all(), new_right.all()),
+                    left: new_left,
+                    right: new_right,
+                }
+            }
+        }
+    }
+
+    /// Apply a modifier to the value at `index` only, returning the new SegTree
+    ///
+    /// The same as `self.apply(index..=index, m)`, but it follows the single
+    /// path to the leaf.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= self.size()`, like [`Self::set`].
+    pub fn apply_point(&self, index: usize, m: M) -> Self {
+        assert!(
+            index < self.size(),
+            "index {} out of bounds for tree of size {}",
+            index,
+            self.size()
+        );
+        if m.is_identity() {
+            return self.clone();
+        }
+        self.apply_point_inner(index, m)
+    }
+
+    fn apply_point_inner(&self, index: usize, m: M) -> Self {
+        match self {
+            Self::Empty => Self::Empty,
+            Self::Unit(v) => Self::Unit(m.apply(v.clone())),
+            Self::Branch {
+                size,
+                modifier,
+                left,
+                right,
+                ..
+            } => {
+                let mid = left.size();
+                let (new_left, new_right) = if index < mid {
+                    (
+                        Self::push_down_with(modifier, left, |l| l.apply_point_inner(index, m)),
+                        Self::push_down(modifier, right),
+                    )
+                } else {
+                    (
+                        Self::push_down(modifier, left),
+                        Self::push_down_with(modifier, right, |r| {
+                            r.apply_point_inner(index - mid, m)
+                        }),
+                    )
+                };
+
+                Self::Branch {
+                    size: *size,
+                    modifier: M::empty(),
+                    value: V::merge(new_left.all(), new_right.all()),
+                    left: new_left,
+                    right: new_right,
+                }
+            }
+        }
+    }
+
+    /// Split into the trees of `..index` and `index..`
+    ///
+    /// Pending modifiers on the cut path are pushed down, the other
+    /// subtrees are shared with `self`. Takes O(log n) time and the depth of
+    /// neither half exceeds the depth of `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > self.size()`.
+    pub fn split(&self, index: usize) -> (Self, Self) {
+        assert!(
+            index <= self.size(),
+            "index {} out of bounds for tree of size {}",
+            index,
+            self.size()
+        );
+        self.split_inner(index)
+    }
+
+    fn split_inner(&self, index: usize) -> (Self, Self) {
+        match self {
+            Self::Branch {
+                size,
+                modifier,
+                left,
+                right,
+                ..
+            } if 0 < index && index < *size => {
+                let mid = left.size();
+                if index < mid {
+                    let (a, b) = Self::with_pushed(modifier, left, |l| l.split_inner(index));
+                    (
+                        a,
+                        Self::join(Self::share(b), Self::push_down(modifier, right)),
+                    )
+                } else {
+                    let (a, b) = Self::with_pushed(modifier, right, |r| r.split_inner(index - mid));
+                    (
+                        Self::join(Self::push_down(modifier, left), Self::share(a)),
+                        b,
+                    )
+                }
+            }
+            _ if index == 0 => (Self::Empty, self.clone()),
+            _ => (self.clone(), Self::Empty),
+        }
+    }
+
+    /// Keep only the first `new_len` values, like `Vec::truncate`
+    ///
+    /// The same as `self.split(new_len).0` in O(log n), or a clone of `self`
+    /// if it is not longer than `new_len`.
+    pub fn truncate(&self, new_len: usize) -> Self {
+        if new_len >= self.size() {
+            return self.clone();
+        }
+        self.split_inner(new_len).0
+    }
+
+    /// Keep `..index` in `self` and return `index..`, like `Vec::split_off`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > self.size()`.
+    pub fn split_off(&mut self, index: usize) -> Self {
+        let (head, tail) = self.split(index);
+        *self = head;
+        tail
+    }
+
+    /// Put `left` and `right` side by side, in case either of them is empty
+    fn join(left: R::Ptr<Self>, right: R::Ptr<Self>) -> Self {
+        match (&*left, &*right) {
+            (Self::Empty, _) => Self::unshare(right),
+            (_, Self::Empty) => Self::unshare(left),
+            _ => Self::branch(left, right),
+        }
+    }
+
+    /// Concatenate `self` and `other`, so the values of `other` follow those of `self`
+    ///
+    /// The result is kept weight balanced: the larger tree is descended
+    /// along its inner spine until the subtree there is comparable to the
+    /// smaller one, then rebalanced on the way back with rotations.
+    /// Takes O(log n) time and shares all the untouched subtrees.
+    pub fn concat(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Self::Empty, _) => other.clone(),
+            (_, Self::Empty) => self.clone(),
+            _ => Self::join_balanced(Self::share(self.clone()), Self::share(other.clone())),
+        }
+    }
+
+    /// Append the items of `iter` after the values of `self`
+    ///
+    /// The items are built into a tree of their own and [`Self::concat`]ed,
+    /// so this takes O(k + log n) for k items and shares all the nodes of
+    /// `self`.
+    pub fn extend<I: IntoIterator<Item = V>>(&self, iter: I) -> Self {
+        self.concat(&Self::from_values(iter))
+    }
+
+    /// Insert `value` at `index`, shifting the values after it to the right
+    ///
+    /// Takes O(log n) time by a [`Self::split`] and two [`Self::concat`]s.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > self.size()`.
+    pub fn insert(&self, index: usize, value: V) -> Self {
+        let (left, right) = self.split(index);
+        left.concat(&Self::Unit(value)).concat(&right)
+    }
+
+    /// Remove the value at `index`, shifting the values after it to the left
+    ///
+    /// Takes O(log n) time by two [`Self::split`]s and a [`Self::concat`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= self.size()`.
+    pub fn remove(&self, index: usize) -> Self {
+        assert!(
+            index < self.size(),
+            "index {} out of bounds for tree of size {}",
+            index,
+            self.size()
+        );
+        let (left, right) = self.split(index);
+        left.concat(&right.split(1).1)
+    }
+
+    /// Merge two trees of the same size position by position,
+    /// so the value at `i` is `self.get(i).merge(other.get(i))`
+    ///
+    /// The result takes the shape of `self`. When `other` has the same shape,
+    /// e.g. both are versions of one tree, this takes O(n) time, otherwise
+    /// `other` is split along the way which costs another O(log n) per node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sizes differ.
+    pub fn pointwise_merge(&self, other: &Self) -> Self {
+        assert_eq!(
+            self.size(),
+            other.size(),
+            "cannot merge trees of different sizes"
+        );
+        match (self, other) {
+            (Self::Empty, _) => Self::Empty,
+            (Self::Unit(a), Self::Unit(b)) => Self::Unit(V::merge(a.clone(), b.clone())),
+            _ => {
+                let (a_left, a_right) = self.expose();
+                let (b_left, b_right) = match other {
+                    Self::Branch { left, .. } if left.size() == a_left.size() => other.expose(),
+                    _ => {
+                        let (b_left, b_right) = other.split_inner(a_left.size());
+                        (Self::share(b_left), Self::share(b_right))
+                    }
+                };
+                Self::node(
+                    a_left.pointwise_merge(&b_left),
+                    a_right.pointwise_merge(&b_right),
+                )
+            }
+        }
+    }
+
+    /// Whether two subtrees of these sizes may be siblings
+    fn balanced(a: usize, b: usize) -> bool {
+        a <= 3 * b && b <= 3 * a
+    }
+
+    /// The children of a branch, with its pending modifier pushed down
+    fn expose(&self) -> (R::Ptr<Self>, R::Ptr<Self>) {
+        match self {
+            Self::Branch {
+                modifier,
+                left,
+                right,
+                ..
+            } => (
+                Self::push_down(modifier, left),
+                Self::push_down(modifier, right),
+            ),
+            _ => unreachable!("only a branch can be heavier than another tree"),
+        }
+    }
+
+    /// Concatenate two non-empty trees, descending into the heavier one
+    fn join_balanced(left: R::Ptr<Self>, right: R::Ptr<Self>) -> Self {
+        if Self::balanced(left.size(), right.size()) {
+            Self::branch(left, right)
+        } else if left.size() > right.size() {
+            let (ll, lr) = left.expose();
+            Self::rebalance(ll, Self::share(Self::join_balanced(lr, right)))
+        } else {
+            let (rl, rr) = right.expose();
+            Self::rebalance(Self::share(Self::join_balanced(left, rl)), rr)
+        }
+    }
+
+    /// [`Self::branch`] with a single or double rotation if one side is too heavy
+    fn rebalance(left: R::Ptr<Self>, right: R::Ptr<Self>) -> Self {
+        let node = |left, right| Self::share(Self::branch(left, right));
+        if Self::balanced(left.size(), right.size()) {
+            Self::branch(left, right)
+        } else if left.size() > right.size() {
+            let (ll, lr) = left.expose();
+            if lr.size() < 2 * ll.size() || matches!(*lr, Self::Unit(_)) {
+                Self::branch(ll, node(lr, right))
+            } else {
+                let (lrl, lrr) = lr.expose();
+                Self::branch(node(ll, lrl), node(lrr, right))
+            }
+        } else {
+            let (rl, rr) = right.expose();
+            if rl.size() < 2 * rr.size() || matches!(*rl, Self::Unit(_)) {
+                Self::branch(node(left, rl), rr)
+            } else {
+                let (rll, rlr) = rl.expose();
+                Self::branch(node(left, rll), node(rlr, rr))
+            }
+        }
+    }
+
+    /// Apply a modifier to a SegTree
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The range to modify, e.g. `start..end`, `..=end` or `..`
+    ///   for the whole tree
+    /// * `m` - The modifier to apply
+    ///
+    /// Applying an identity, see [`Monoid::is_identity`], only clones the root.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn apply(&self, range: impl RangeBounds<usize>, m: M) -> Self {
+        let range = self.resolve_range(range);
+        if range.is_empty() || m.is_identity() {
+            return self.clone();
+        }
+        self.apply_inner(range, m)
+    }
+
+    /// Apply `m` on `l..=r` with both ends included, as problem statements put it
+    ///
+    /// The same as `apply(l..=r, m)`, `r == self.size() - 1` reaches the end.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `r >= self.size()`, or `l > r + 1`.
+    pub fn apply_inclusive(&self, l: usize, r: usize, m: M) -> Self {
+        self.apply(l..=r, m)
+    }
+
+    /// [`SegTree::apply`], also returning the indices it touched
+    ///
+    /// The returned range is `range` resolved to `start..end`, or the empty
+    /// `start..start` if nothing changed, because `range` is empty or `m` is
+    /// an identity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn apply_tracked(&self, range: impl RangeBounds<usize>, m: M) -> (Self, Range<usize>) {
+        let range = self.resolve_range(range);
+        if range.is_empty() || m.is_identity() {
+            return (self.clone(), range.start..range.start);
+        }
+        (self.apply_inner(range.clone(), m), range)
+    }
+
+    /// Replace each value `v` at an index `i` in `range` by `f(i, v)`
+    ///
+    /// An escape hatch for updates that no modifier can express: every value
+    /// in the range is visited, with the pending modifiers above it pushed
+    /// down first, and its ancestors are recomputed, so it takes
+    /// O((r - l) + log n) instead of the O(log n) of a lazy [`SegTree::apply`].
+    /// The rest of the tree is shared.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn map_range<F: Fn(usize, V) -> V>(&self, range: impl RangeBounds<usize>, f: F) -> Self {
+        let range = self.resolve_range(range);
+        if range.is_empty() {
+            return self.clone();
+        }
+        self.map_range_inner(0, range, &f)
+    }
+
+    /// `range` is a non-empty part of the indices of `self`, which start at `offset`
+    fn map_range_inner<F: Fn(usize, V) -> V>(
+        &self,
+        offset: usize,
+        range: Range<usize>,
+        f: &F,
+    ) -> Self {
+        match self {
+            Self::Empty => Self::Empty,
+            Self::Unit(v) => Self::Unit(f(offset, v.clone())),
+            Self::Branch {
+                size,
+                modifier,
+                left,
+                right,
+                ..
+            } => {
+                let mid = offset + left.size();
+                let new_left = if range.start < mid {
+                    let range = range.start..min(range.end, mid);
+                    Self::push_down_with(modifier, left, |l| l.map_range_inner(offset, range, f))
+                } else {
+                    Self::push_down(modifier, left)
+                };
+                let new_right = if mid < range.end {
+                    let range = max(range.start, mid)..range.end;
+                    Self::push_down_with(modifier, right, |r| r.map_range_inner(mid, range, f))
+                } else {
+                    Self::push_down(modifier, right)
+                };
+                Self::Branch {
+                    size: *size,
+                    modifier: M::empty(),
+                    value: V::merge(new_left.all(), new_right.all()),
+                    left: new_left,
+                    right: new_right,
+                }
+            }
+        }
+    }
+
+    /// [`SegTree::apply`], or `None` instead of panicking if the range is
+    /// reversed or ends beyond `self.size()`
+    pub fn apply_checked(&self, range: impl RangeBounds<usize>, m: M) -> Option<Self> {
+        self.checked_range(range).map(|range| self.apply(range, m))
+    }
+
+    /// Set every value in `range` to `value`, returning the new tree
+    ///
+    /// Works with any modifier, without an [`Assign`](monoids::Assign) in
+    /// `M`: the covered subtrees are replaced by subtrees of `value` only,
+    /// whose equal-sized parts share their nodes, and the merged values are
+    /// recomputed by `V::merge`, e.g. a `(Sum, Size)` range becomes worth
+    /// `value` times its length. Takes O(log² n) new nodes.
+    ///
+    /// The new subtrees are shaped the way [`SegTree::build`] shapes them, so
+    /// a built tree keeps its shape and versions made from it by `fill` and
+    /// [`SegTree::apply`] can be compared by [`SegTree::kth_in_difference`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn fill(&self, range: impl RangeBounds<usize>, value: V) -> Self {
+        let range = self.resolve_range(range);
+        if range.is_empty() {
+            return self.clone();
+        }
+        self.fill_inner(range, &value, &mut Vec::new())
+    }
+
+    /// `size` copies of `value`, reusing the subtrees already in `filled`
+    ///
+    /// Shaped as [`Self::build`] shapes `size` values, with the largest power
+    /// of two below `size` on the left.
+    fn filled(size: usize, value: &V, filled: &mut Vec<(usize, R::Ptr<Self>)>) -> R::Ptr<Self> {
+        if let Some((_, node)) = filled.iter().find(|(s, _)| *s == size) {
+            return node.clone();
+        }
+        let node = Self::share(if size == 1 {
+            Self::Unit(value.clone())
+        } else {
+            let half = 1 << (size - 1).ilog2();
+            let left = Self::filled(half, value, filled);
+            let right = Self::filled(size - half, value, filled);
+            Self::Branch {
+                size,
+                modifier: M::empty(),
+                value: V::merge(left.all(), right.all()),
+                left,
+                right,
+            }
+        });
+        filled.push((size, node.clone()));
+        node
+    }
+
+    fn fill_inner(
+        &self,
+        range: Range<usize>,
+        value: &V,
+        filled: &mut Vec<(usize, R::Ptr<Self>)>,
+    ) -> Self {
+        match self {
+            Self::Empty => Self::Empty,
+            Self::Unit(_) => Self::Unit(value.clone()),
+            Self::Branch {
+                size,
+                modifier,
+                left,
+                right,
+                ..
+            } => {
+                if range.start == 0 && *size <= range.end {
+                    return Self::unshare(Self::filled(*size, value, filled));
+                }
+                let mid = left.size();
+                let (new_left, new_right) = if range.end <= mid {
+                    (
+                        Self::push_down_with(modifier, left, |l| {
+                            l.fill_inner(range, value, filled)
+                        }),
+                        Self::push_down(modifier, right),
+                    )
+                } else if mid <= range.start {
+                    let range = range.start - mid..range.end - mid;
+                    (
+                        Self::push_down(modifier, left),
+                        Self::push_down_with(modifier, right, |r| {
+                            r.fill_inner(range, value, filled)
+                        }),
+                    )
+                } else {
+                    (
+                        Self::push_down_with(modifier, left, |l| {
+                            l.fill_inner(range.start..mid, value, filled)
+                        }),
+                        Self::push_down_with(modifier, right, |r| {
+                            r.fill_inner(0..range.end - mid, value, filled)
+                        }),
+                    )
+                };
+                Self::Branch {
+                    size: *size,
+                    modifier: M::empty(),
+                    value: V::merge(new_left.all(), new_right.all()),
+                    left: new_left,
+                    right: new_right,
+                }
+            }
+        }
+    }
+
+    /// Same as `*self = self.apply(range, m)`, but in place where possible
+    ///
+    /// Nodes that no other version refers to are updated in place instead
+    /// of being copied, so a tree used as a single version is updated
+    /// without allocating. Those shared with another version are copied on
+    /// write, which leaves that version untouched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn apply_mut(&mut self, range: impl RangeBounds<usize>, m: M) {
+        let range = self.resolve_range(range);
+        if range.is_empty() || m.is_identity() {
+            return;
+        }
+        self.apply_mut_inner(range, m)
+    }
+
+    /// All the versions along `updates`: element `k` is `base` after the first `k` of them
+    ///
+    /// Each version shares all but O(log n) nodes with the previous one.
+    /// See [`Versioned`] for a history that can also be forked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any range is reversed or ends beyond `base.size()`.
+    pub fn history<I: IntoIterator<Item = (Range<usize>, M)>>(base: Self, updates: I) -> Vec<Self> {
+        let updates = updates.into_iter();
+        let mut versions = Vec::with_capacity(updates.size_hint().0 + 1);
+        versions.push(base);
+        for (range, m) in updates {
+            let next = versions[versions.len() - 1].apply(range, m);
+            versions.push(next);
+        }
+        versions
+    }
+
+    /// Apply each `(range, m)` of `updates` in the given order, returning the new tree
+    ///
+    /// The same as chaining [`Self::apply`] on them, which matters when the
+    /// modifiers don't commute, but the nodes copied by one update are
+    /// updated in place by the next ones through [`Self::apply_mut`], so
+    /// the intermediate trees are never allocated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any range is reversed or ends beyond `self.size()`.
+    pub fn apply_batch(&self, updates: &[(Range<usize>, M)]) -> Self {
+        let mut tree = self.clone();
+        for (range, m) in updates {
+            tree.apply_mut(range.clone(), m.clone());
+        }
+        tree
+    }
+
+    fn apply_all_mut(&mut self, m: M) {
+        match self {
+            Self::Empty => {}
+            Self::Unit(v) => *v = m.apply(core::mem::replace(v, V::empty())),
+            Self::Branch {
+                modifier,
+                value,
+                left,
+                right,
+                ..
+            } => {
+                if m.reverses() {
+                    core::mem::swap(left, right);
+                }
+                *value = m.apply(core::mem::replace(value, V::empty()));
+                *modifier = M::merge(m, core::mem::replace(modifier, M::empty()));
+            }
+        }
+    }
+
+    fn apply_mut_inner(&mut self, range: Range<usize>, m: M) {
+        match self {
+            Self::Empty => {}
+            Self::Unit(_) => {
+                if range.contains(&0) {
+                    self.apply_all_mut(m);
+                }
+            }
+            Self::Branch {
+                size,
+                modifier,
+                value,
+                left,
+                right,
+            } => {
+                if range.start == 0 && *size <= range.end {
+                    return self.apply_all_mut(m);
+                }
+                let mid = left.size();
+                // push down
+                // to ensure the top modifier is the newest one.
+                let pending = core::mem::replace(modifier, M::empty());
+                if !pending.is_identity() {
+                    Ref::make_mut(left).apply_all_mut(pending.clone());
+                    Ref::make_mut(right).apply_all_mut(pending);
+                }
+
+                if range.end <= mid {
+                    Ref::make_mut(left).apply_mut_inner(range, m);
+                } else if mid <= range.start {
+                    Ref::make_mut(right).apply_mut_inner(range.start - mid..range.end - mid, m);
+                } else {
+                    Ref::make_mut(left).apply_mut_inner(range.start..mid, m.clone());
+                    Ref::make_mut(right).apply_mut_inner(0..range.end - mid, m);
+                }
+                *value = V::merge(left.all(), right.all());
+            }
+        }
+    }
+
+    /// `child` with the pending `modifier` of its parent pushed into it,
+    /// shared as it is when there is nothing to push
+    fn push_down(modifier: &M, child: &R::Ptr<Self>) -> R::Ptr<Self> {
+        if modifier.is_identity() {
+            child.clone()
+        } else {
+            Self::share(child.apply_all(modifier.clone()))
+        }
+    }
+
+    /// `update(child)` after pushing the pending `modifier` of its parent
+    /// into `child`, which is copied only once, if at all
+    fn push_down_with<F: FnOnce(&Self) -> Self>(
+        modifier: &M,
+        child: &R::Ptr<Self>,
+        update: F,
+    ) -> R::Ptr<Self> {
+        Self::share(Self::with_pushed(modifier, child, update))
+    }
+
+    /// `f(child)` after pushing the pending `modifier` of its parent into
+    /// `child`, without copying it if there is nothing to push
+    fn with_pushed<T, F: FnOnce(&Self) -> T>(modifier: &M, child: &R::Ptr<Self>, f: F) -> T {
+        if modifier.is_identity() {
+            f(child)
+        } else {
+            f(&child.apply_all(modifier.clone()))
+        }
+    }
+
+    fn apply_inner(&self, range: Range<usize>, m: M) -> Self {
+        match self {
+            Self::Empty => Self::Empty,
+            Self::Unit(v) => {
+                if range.contains(&0) {
+                    Self::Unit(m.apply(v.clone()))
+                } else {
+                    Self::Unit(v.clone())
+                }
+            }
+            Self::Branch {
+                size,
+                modifier,
+                value,
+                left,
+                right,
+            } => {
+                if range.start == 0 && *size <= range.end {
+                    Self::Branch {
+                        size: *size,
+                        value: m.apply(value.clone()),
+                        modifier: M::merge(m, modifier.clone()),
+                        left: left.clone(),
+                        right: right.clone(),
+                    }
+                } else {
+                    let mid = left.size();
+                    // push down
+                    // to ensure the top modifier is the newest one.
+                    // `m` is only cloned when both children are affected
+                    let (new_left, new_right) = if range.end <= mid {
+                        (
+                            Self::push_down_with(modifier, left, |l| l.apply_inner(range, m)),
+                            Self::push_down(modifier, right),
+                        )
+                    } else if mid <= range.start {
+                        let range = range.start - mid..range.end - mid;
+                        (
+                            Self::push_down(modifier, left),
+                            Self::push_down_with(modifier, right, |r| r.apply_inner(range, m)),
+                        )
+                    } else {
+                        (
+                            Self::push_down_with(modifier, left, |l| {
+                                l.apply_inner(range.start..mid, m.clone())
+                            }),
+                            Self::push_down_with(modifier, right, |r| {
+                                r.apply_inner(0..range.end - mid, m)
+                            }),
+                        )
+                    };
+
+                    Self::Branch {
+                        size: *size,
+                        modifier: M::empty(),
+                        value: V::merge(new_left.all(), new_right.all()),
+                        left: new_left,
+                        right: new_right,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<V, M, R: SharedRef> SegTree<V, monoids::Flip<M>, R>
+where
+    V: Reversible + Monoid + Clone,
+    M: Applier<V> + Monoid + Clone,
+{
+    /// Reverse the order of the values in `range`, returning the new tree
+    ///
+    /// The range is split off, given a pending reversal at its root and
+    /// concatenated back, in O(log n). The reversal is pushed down like any
+    /// modifier, see [`monoids::Flip`], so the untouched subtrees are shared.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn reverse(&self, range: impl RangeBounds<usize>) -> Self {
+        let range = self.resolve_range(range);
+        if range.len() < 2 {
+            return self.clone();
+        }
+        let (head, rest) = self.split_inner(range.start);
+        let (middle, tail) = rest.split_inner(range.len());
+        head.concat(&middle.apply_all(monoids::Flip::reversal()))
+            .concat(&tail)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<V, M> SegTree<V, M, ArcRef>
+where
+    V: Monoid + Clone + Send + Sync,
+    M: Applier<V> + Monoid + Clone + Send + Sync,
+{
+    /// Below this many values [`Self::par_build`] does not split the work any further
+    const PAR_THRESHOLD: usize = 1 << 14;
+
+    /// [`Self::build`] on the rayon thread pool, giving the same tree
+    ///
+    /// The left and right subtrees are built in parallel down to
+    /// [`Self::PAR_THRESHOLD`] values, so small trees are built sequentially.
+    pub fn par_build<F: Fn(usize) -> V + Sync>(len: usize, init: F) -> Self {
+        Self::par_build_inner(0..len, &init)
+    }
+
+    fn par_build_inner<F: Fn(usize) -> V + Sync>(range: Range<usize>, init: &F) -> Self {
+        if range.len() <= Self::PAR_THRESHOLD {
+            return Self::from_values(range.map(init));
+        }
+        // the bottom-up build puts the largest power of two on the left
+        let mid = range.start + range.len().next_power_of_two() / 2;
+        let (left, right) = rayon::join(
+            || Self::par_build_inner(range.start..mid, init),
+            || Self::par_build_inner(mid..range.end, init),
+        );
+        Self::node(left, right)
+    }
+}
+
+impl<V: Monoid + Clone, M: Applier<V> + Group + Clone, R: SharedRef> SegTree<V, M, R> {
+    /// Undo `self.apply(range, m)` by applying the inverse of `m` on `range`
+    ///
+    /// This undoes the latest `apply` on `range`, or any earlier one when
+    /// the modifiers commute. `M` has to be a [`Group`], so it does not work
+    /// with modifiers that have no inverse, e.g. assigning `Max`/`Min`.
+    pub fn unapply(&self, range: impl RangeBounds<usize>, m: M) -> Self {
+        self.apply(range, m.inverse())
+    }
+}
+
+#[cfg(feature = "dot")]
+impl<V: core::fmt::Display, M: core::fmt::Display, R: SharedRef> SegTree<V, M, R> {
+    /// Describe the nodes of the tree in Graphviz DOT, e.g. for `dot -Tsvg`
+    ///
+    /// Every node is labeled with the range it covers, its `value` and, for
+    /// branches, its pending `modifier`. Values are shown as stored, i.e.
+    /// without the modifiers pending at the ancestors.
+    ///
+    /// A subtree shared by several parents is drawn once under each of
+    /// them, so that every box shows the range it covers at that place.
+    pub fn to_dot(&self) -> alloc::string::String {
+        let mut dot = alloc::string::String::from("digraph SegTree {\n    node [shape=box];\n");
+        if !matches!(self, Self::Empty) {
+            self.write_dot(&mut dot, 0, &mut 0);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Writes the subtree starting at index `offset`, numbering its nodes from `next_id`
+    fn write_dot(&self, dot: &mut alloc::string::String, offset: usize, next_id: &mut usize) {
+        use core::fmt::Write;
+
+        let id = *next_id;
+        *next_id += 1;
+        // the `\n`s between lines are DOT escapes, the shown values only
+        // need their quotes and backslashes escaped
+        let escape = |x: &dyn core::fmt::Display| {
+            alloc::format!("{}", x)
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+        };
+        let range = offset..offset + self.size();
+        let label = match self {
+            Self::Empty => alloc::format!("{:?}", range),
+            Self::Unit(v) => alloc::format!("{:?}\\nvalue: {}", range, escape(v)),
+            Self::Branch {
+                value, modifier, ..
+            } => alloc::format!(
+                "{:?}\\nvalue: {}\\nmodifier: {}",
+                range,
+                escape(value),
+                escape(modifier)
+            ),
+        };
+        writeln!(dot, "    n{} [label=\"{}\"];", id, label).unwrap();
+        if let Self::Branch { left, right, .. } = self {
+            for (child, offset) in [(left, offset), (right, offset + left.size())] {
+                writeln!(dot, "    n{} -> n{};", id, *next_id).unwrap();
+                child.write_dot(dot, offset, next_id);
+            }
+        }
+    }
+}
+
+impl<V: Monoid + Clone, M: Applier<V> + Monoid + Clone, R: SharedRef> core::iter::FromIterator<V>
+    for SegTree<V, M, R>
+{
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = V>>(iter: T) -> Self {
+        Self::from_values(iter)
+    }
+}
+
+impl<V: Monoid + Clone, M: Applier<V> + Monoid + Clone, R: SharedRef> From<&[V]>
+    for SegTree<V, M, R>
+{
+    #[inline]
+    fn from(values: &[V]) -> Self {
+        Self::from_values(values.iter().cloned())
+    }
+}
+
+impl<V: Monoid + Clone, M: Applier<V> + Monoid + Clone, R: SharedRef> From<Vec<V>>
+    for SegTree<V, M, R>
+{
+    #[inline]
+    fn from(values: Vec<V>) -> Self {
+        Self::from_values(values)
+    }
+}
+
+impl<'a, V: Clone + Semigroup, M: Applier<V> + Semigroup + Clone, R: SharedRef> Iterator
+    for Iter<'a, V, M, R>
+{
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, acc)) = self.stack.pop() {
+            match node {
+                SegTree::Empty => {}
+                SegTree::Unit(v) => return Some(acc.apply(v.clone())),
+                SegTree::Branch {
+                    modifier,
+                    left,
+                    right,
+                    ..
+                } => {
+                    let (left, right) = SegTree::<V, M, R>::in_order(&acc, left, right);
+                    let acc = M::merge(acc, modifier.clone());
+                    self.stack.push((right, acc.clone()));
+                    self.stack.push((left, acc));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<V: Clone + Semigroup, M: Applier<V> + Semigroup + Clone, R: SharedRef> Iterator
+    for IntoIter<V, M, R>
+{
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, acc)) = self.stack.pop() {
+            match node {
+                SegTree::Empty => {}
+                SegTree::Unit(v) => return Some(acc.apply(v)),
+                SegTree::Branch {
+                    modifier,
+                    left,
+                    right,
+                    ..
+                } => {
+                    let (left, right) = SegTree::<V, M, R>::in_order(&acc, left, right);
+                    let acc = M::merge(acc, modifier);
+                    self.stack.push((SegTree::unshare(right), acc.clone()));
+                    self.stack.push((SegTree::unshare(left), acc));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Consumes the tree, yielding the values with all pending modifiers applied
+///
+/// Nodes that no other tree refers to are taken apart without cloning their
+/// values, the ones still shared with another version are cloned.
+impl<V: Monoid + Clone, M: Applier<V> + Monoid + Clone, R: SharedRef> IntoIterator
+    for SegTree<V, M, R>
+{
+    type Item = V;
+    type IntoIter = IntoIter<V, M, R>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            stack: vec![(self, M::empty())],
+        }
+    }
+}
+
+/// Compares the values with all pending modifiers applied in O(n),
+/// so trees with the same contents are equal no matter how the modifiers
+/// are distributed or whether any node is shared.
+impl<V, M, R: SharedRef> PartialEq for SegTree<V, M, R>
+where
+    V: Monoid + Clone + PartialEq,
+    M: Applier<V> + Monoid + Clone,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.size() == other.size() && self.iter().eq(other.iter())
+    }
+}
+
+impl<V, M, R: SharedRef> Eq for SegTree<V, M, R>
+where
+    V: Monoid + Clone + Eq,
+    M: Applier<V> + Monoid + Clone,
+{
+}
+
+/// Hashes the values with all pending modifiers applied in O(n), like a
+/// slice of them, so that trees equal by [`PartialEq`] hash the same.
+impl<V, M, R: SharedRef> core::hash::Hash for SegTree<V, M, R>
+where
+    V: Monoid + Clone + core::hash::Hash,
+    M: Applier<V> + Monoid + Clone,
+{
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        state.write_usize(self.size());
+        for v in self.iter() {
+            v.hash(state);
+        }
+    }
+}
+
+/// Shows the values with all pending modifiers applied, like `[3, 1, 4, 1, 5]`
+impl<V, M, R: SharedRef> core::fmt::Display for SegTree<V, M, R>
+where
+    V: Monoid + Clone + core::fmt::Display,
+    M: Applier<V> + Monoid + Clone,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("[")?;
+        for (i, v) in self.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            v.fmt(f)?;
+        }
+        f.write_str("]")
+    }
+}
+
+/// Serialized as the sequence of values with all pending modifiers applied
+#[cfg(feature = "serde")]
+impl<V, M, R: SharedRef> serde::Serialize for SegTree<V, M, R>
+where
+    V: Monoid + Clone + serde::Serialize,
+    M: Applier<V> + Monoid + Clone,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, V, M, R: SharedRef> serde::Deserialize<'de> for SegTree<V, M, R>
+where
+    V: Monoid + Clone + serde::Deserialize<'de>,
+    M: Applier<V> + Monoid + Clone,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<V>::deserialize(deserializer).map(Self::from)
+    }
+}
@@ -0,0 +1,995 @@
+//! Ready-made values and modifiers for [`SegTree`](super::SegTree)
+//!
+//! Values that need to know how many elements they cover are paired with
+//! [`Size`], e.g. `(Sum<T>, Size)`, each element starts with `Size(1)`
+//! (which is also `Size::default()`).
+//!
+//! Range maximum / minimum use [`Max`] and [`Min`], whose identities are
+//! the `Max::NegInf` and `Min::Inf` sentinels, so every `Ord` type works:
+//!
+//! ```
+//! use solution::seg_tree::{monoids::*, SegTree};
+//!
+//! let values = vec![3, 1, 4, 1, 5, 9, 2, 6].into_iter().map(Max::Has).collect::<Vec<_>>();
+//! let tree = SegTree::<Max<i64>, Plus<i64>>::from(values);
+//! assert_eq!(tree.query(2..7), Max::Has(9));
+//! assert_eq!(tree.apply(5..6, Plus(-10)).query(2..7), Max::Has(5));
+//! ```
+//!
+//! [`Matrix`] values merge by multiplication in order, e.g. the Fibonacci
+//! numbers:
+//!
+//! ```
+//! use solution::seg_tree::{monoids::*, SegTree};
+//!
+//! let step = Matrix([[1u64, 1], [1, 0]]);
+//! let tree: SegTree<_, NoOp> = std::iter::repeat(step).take(90).collect();
+//! // [[F(n + 1), F(n)], [F(n), F(n - 1)]]
+//! assert_eq!(tree.query(10..20).0[0][1], 55);
+//! assert_eq!(tree.query(..90).0[0][1], 2880067194370816120);
+//! ```
+
+use super::{Applier, Count, Reversible};
+use crate::traits::*;
+
+use core::{fmt, ops::Add};
+
+pub use crate::traits::monoid::Size;
+pub use crate::traits::semigroup::{
+    BitAnd, BitOr, CheckedSum, Gcd, Lcm, Matrix, Max, Min, Single, Sum, TotalOrd, Xor,
+};
+
+macro_rules! impl_count {
+    [$($t:ty),*] => {
+        $(
+        impl Count for Sum<$t> {
+            type Count = $t;
+            #[inline]
+            fn count(&self) -> $t {
+                self.0
+            }
+        }
+        )*
+    };
+}
+
+impl_count![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize, f32, f64];
+
+impl Count for Size {
+    type Count = usize;
+    #[inline]
+    fn count(&self) -> usize {
+        self.0
+    }
+}
+
+/// Counts by the first component, e.g. `(Sum<T>, Size)` counts by the sum
+impl<A: Count> Count for (A, Size) {
+    type Count = A::Count;
+    #[inline]
+    fn count(&self) -> A::Count {
+        self.0.count()
+    }
+}
+
+impl<T: Add<Output = T>> Reversible for Sum<T> {
+    #[inline]
+    fn swap_sides(self) -> Self {
+        self
+    }
+}
+
+impl<T: Ord> Reversible for Max<T> {
+    #[inline]
+    fn swap_sides(self) -> Self {
+        self
+    }
+}
+
+impl<T: Ord> Reversible for Min<T> {
+    #[inline]
+    fn swap_sides(self) -> Self {
+        self
+    }
+}
+
+impl Reversible for Size {
+    #[inline]
+    fn swap_sides(self) -> Self {
+        self
+    }
+}
+
+impl<A: Reversible, B: Reversible> Reversible for (A, B) {
+    #[inline]
+    fn swap_sides(self) -> Self {
+        (self.0.swap_sides(), self.1.swap_sides())
+    }
+}
+
+/// The modifier that does nothing
+///
+/// It is the right choice for `M` whenever [`apply`](super::SegTree::apply)
+/// is never called, e.g. `SegTree<Max<i64>, NoOp>` for static range maximum.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoOp;
+
+impl Semigroup for NoOp {
+    #[inline]
+    fn merge(self, _: Self) -> Self {
+        NoOp
+    }
+}
+
+impl CommutativeSemigroup for NoOp {}
+
+impl Monoid for NoOp {
+    #[inline]
+    fn empty() -> Self {
+        NoOp
+    }
+
+    #[inline]
+    fn is_identity(&self) -> bool {
+        true
+    }
+}
+
+impl<V: Semigroup> Applier<V> for NoOp {
+    #[inline]
+    fn apply(&self, to: V) -> V {
+        to
+    }
+}
+
+/// Add a constant to every element, applies to `(Sum<T>, Size)`
+///
+/// # Example
+///
+/// ```
+/// use solution::seg_tree::{monoids::*, SegTree};
+///
+/// let tree: SegTree<_, Plus<i64>> = (1..=5).map(|x| (Sum(x), Size(1))).collect();
+/// let tree = tree.apply(1..4, Plus(10));
+/// assert_eq!(tree.query(..).0, Sum(45));
+/// assert_eq!(tree.query(3..).0, Sum(19));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Plus<T>(pub T);
+
+macro_rules! impl_plus {
+    [$($t:ty),*] => {
+        $(
+        impl Semigroup for Plus<$t> {
+            #[inline]
+            fn merge(self, other: Self) -> Self {
+                Plus(self.0 + other.0)
+            }
+        }
+        impl CommutativeSemigroup for Plus<$t> {}
+        impl Monoid for Plus<$t> {
+            #[inline]
+            fn empty() -> Self {
+                Plus(0 as $t)
+            }
+
+            #[inline]
+            fn is_identity(&self) -> bool {
+                self.0 == 0 as $t
+            }
+        }
+        impl Applier<(Sum<$t>, Size)> for Plus<$t> {
+            #[inline]
+            fn apply(&self, (Sum(s), n): (Sum<$t>, Size)) -> (Sum<$t>, Size) {
+                (Sum(s + self.0 * n.0 as $t), n)
+            }
+        }
+        )*
+    };
+}
+
+macro_rules! impl_plus_checked {
+    [$($t:ty),*] => {
+        $(
+        impl Applier<(CheckedSum<$t>, Size)> for Plus<$t> {
+            /// # Panics
+            ///
+            /// Panics if the new sum overflows.
+            #[inline]
+            fn apply(&self, (CheckedSum(s), n): (CheckedSum<$t>, Size)) -> (CheckedSum<$t>, Size) {
+                let sum = <$t>::try_from(n.0)
+                    .ok()
+                    .and_then(|n| self.0.checked_mul(n))
+                    .and_then(|d| s.checked_add(d));
+                match sum {
+                    Some(sum) => (CheckedSum(sum), n),
+                    None => panic!("CheckedSum overflowed: {} + {} * {}", s, self.0, n.0),
+                }
+            }
+        }
+        )*
+    };
+}
+
+macro_rules! impl_plus_group {
+    [$($t:ty),*] => {
+        $(
+        impl Group for Plus<$t> {
+            #[inline]
+            fn inverse(self) -> Self {
+                Plus(-self.0)
+            }
+        }
+        )*
+    };
+}
+
+macro_rules! impl_plus_ord {
+    [$($t:ty),*] => {
+        $(
+        impl Applier<Max<$t>> for Plus<$t> {
+            #[inline]
+            fn apply(&self, to: Max<$t>) -> Max<$t> {
+                match to {
+                    Max::NegInf => Max::NegInf,
+                    Max::Has(x) => Max::Has(x + self.0),
+                }
+            }
+        }
+        impl Applier<Min<$t>> for Plus<$t> {
+            #[inline]
+            fn apply(&self, to: Min<$t>) -> Min<$t> {
+                match to {
+                    Min::Inf => Min::Inf,
+                    Min::Has(x) => Min::Has(x + self.0),
+                }
+            }
+        }
+        )*
+    };
+}
+
+/// The minimum of a range and how many times it occurs
+///
+/// The identity is `(T::MAX, 0)`, a count of `0` means there is no element.
+/// Start each element from [`MinCount::new`]. With the [`Plus`] modifier it
+/// answers e.g. "how many zeros are there" for counting covered cells.
+///
+/// # Example
+///
+/// ```
+/// use solution::seg_tree::{monoids::*, SegTree};
+///
+/// let tree: SegTree<_, Plus<i64>> = [2, 1, 3, 1, 1].into_iter().map(MinCount::new).collect();
+/// assert_eq!(tree.query(..), MinCount { min: 1, count: 3 });
+/// assert_eq!(tree.apply(1..2, Plus(5)).query(..2), MinCount { min: 2, count: 1 });
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinCount<T> {
+    pub min: T,
+    pub count: usize,
+}
+
+impl<T> MinCount<T> {
+    /// A single element `x`
+    #[inline]
+    pub fn new(x: T) -> Self {
+        MinCount { min: x, count: 1 }
+    }
+}
+
+impl<T: Ord> Semigroup for MinCount<T> {
+    fn merge(self, other: Self) -> Self {
+        match self.min.cmp(&other.min) {
+            core::cmp::Ordering::Less => self,
+            core::cmp::Ordering::Greater => other,
+            core::cmp::Ordering::Equal => MinCount {
+                min: self.min,
+                count: self.count + other.count,
+            },
+        }
+    }
+}
+
+impl<T: Ord> CommutativeSemigroup for MinCount<T> {}
+
+macro_rules! impl_min_count {
+    [$($t:ty),*] => {
+        $(
+        impl Monoid for MinCount<$t> {
+            #[inline]
+            fn empty() -> Self {
+                MinCount { min: <$t>::MAX, count: 0 }
+            }
+        }
+        impl Applier<MinCount<$t>> for Plus<$t> {
+            /// Shifts the minimum, the count stays the same
+            #[inline]
+            fn apply(&self, to: MinCount<$t>) -> MinCount<$t> {
+                if to.count == 0 {
+                    // the `MAX` sentinel must not overflow
+                    return to;
+                }
+                MinCount { min: to.min + self.0, count: to.count }
+            }
+        }
+        )*
+    };
+}
+
+impl_min_count![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize];
+
+macro_rules! impl_plus_total_ord {
+    [$($t:ty),*] => {
+        $(
+        impl Applier<Max<TotalOrd<$t>>> for Plus<$t> {
+            #[inline]
+            fn apply(&self, to: Max<TotalOrd<$t>>) -> Max<TotalOrd<$t>> {
+                match to {
+                    Max::NegInf => Max::NegInf,
+                    Max::Has(TotalOrd(x)) => Max::Has(TotalOrd(x + self.0)),
+                }
+            }
+        }
+        impl Applier<Min<TotalOrd<$t>>> for Plus<$t> {
+            #[inline]
+            fn apply(&self, to: Min<TotalOrd<$t>>) -> Min<TotalOrd<$t>> {
+                match to {
+                    Min::Inf => Min::Inf,
+                    Min::Has(TotalOrd(x)) => Min::Has(TotalOrd(x + self.0)),
+                }
+            }
+        }
+        )*
+    };
+}
+
+impl_plus![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize, f32, f64];
+impl_plus_group![i8, i16, i32, i64, i128, isize, f32, f64];
+impl_plus_ord![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize];
+impl_plus_total_ord![f32, f64];
+impl_plus_checked![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize];
+
+/// Map every element `x` to `a * x + b`, applies to `(Sum<T>, Size)`
+///
+/// `f.merge(g)` is the composition `f ∘ g`, i.e. `g` is applied first.
+///
+/// # Example
+///
+/// ```
+/// use solution::seg_tree::{monoids::*, SegTree};
+///
+/// let tree: SegTree<_, Affine<i64>> = (1..=4).map(|x| (Sum(x), Size(1))).collect();
+/// // [1, 2, 3, 4] -> [1, 4, 6, 4] -> [1, 5, 7, 5]
+/// let tree = tree.apply(1..3, Affine { a: 2, b: 0 }).apply(1.., Affine { a: 1, b: 1 });
+/// assert_eq!(tree.query(..).0, Sum(18));
+/// assert_eq!(tree.query(2..3).0, Sum(7));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Affine<T> {
+    pub a: T,
+    pub b: T,
+}
+
+macro_rules! impl_affine {
+    [$($t:ty),*] => {
+        $(
+        impl Semigroup for Affine<$t> {
+            /// a1 * (a2 * x + b2) + b1 = (a1 * a2) * x + (a1 * b2 + b1)
+            #[inline]
+            fn merge(self, other: Self) -> Self {
+                Affine {
+                    a: self.a * other.a,
+                    b: self.a * other.b + self.b,
+                }
+            }
+        }
+        impl Monoid for Affine<$t> {
+            /// x = 1 * x + 0
+            #[inline]
+            fn empty() -> Self {
+                Affine { a: 1 as $t, b: 0 as $t }
+            }
+
+            #[inline]
+            fn is_identity(&self) -> bool {
+                self.a == 1 as $t && self.b == 0 as $t
+            }
+        }
+        impl Applier<(Sum<$t>, Size)> for Affine<$t> {
+            #[inline]
+            fn apply(&self, (Sum(s), n): (Sum<$t>, Size)) -> (Sum<$t>, Size) {
+                (Sum(self.a * s + self.b * n.0 as $t), n)
+            }
+        }
+        )*
+    };
+}
+
+impl_affine![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize, f32, f64];
+
+/// A sum together with the number of elements it is made of
+///
+/// The same as `(Sum<T>, Size)` with named fields: merging adds both, the
+/// identity is `{ value: 0, count: 0 }`, and the length-aware [`Plus`],
+/// [`Times`] and [`Affine`] apply to it. Start each element from
+/// [`Counted::new`].
+///
+/// # Example
+///
+/// ```
+/// use solution::seg_tree::{monoids::*, SegTree};
+///
+/// let tree: SegTree<_, Plus<i64>> = (1..=4).map(Counted::new).collect();
+/// let tree = tree.apply(1..3, Plus(10));
+/// assert_eq!(tree.query(1..), Counted { value: 29, count: 3 });
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Counted<T> {
+    pub value: T,
+    pub count: usize,
+}
+
+impl<T> Counted<T> {
+    /// A single element `x`
+    #[inline]
+    pub fn new(x: T) -> Self {
+        Counted { value: x, count: 1 }
+    }
+}
+
+/// Multiply every element by a constant, applies to `(Sum<T>, Size)` and [`Counted`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Times<T>(pub T);
+
+macro_rules! impl_counted {
+    [$($t:ty),*] => {
+        $(
+        impl Semigroup for Counted<$t> {
+            #[inline]
+            fn merge(self, other: Self) -> Self {
+                Counted {
+                    value: self.value + other.value,
+                    count: self.count + other.count,
+                }
+            }
+        }
+        impl CommutativeSemigroup for Counted<$t> {}
+        impl Monoid for Counted<$t> {
+            #[inline]
+            fn empty() -> Self {
+                Counted { value: 0 as $t, count: 0 }
+            }
+        }
+        impl Semigroup for Times<$t> {
+            #[inline]
+            fn merge(self, other: Self) -> Self {
+                Times(self.0 * other.0)
+            }
+        }
+        impl CommutativeSemigroup for Times<$t> {}
+        impl Monoid for Times<$t> {
+            #[inline]
+            fn empty() -> Self {
+                Times(1 as $t)
+            }
+
+            #[inline]
+            fn is_identity(&self) -> bool {
+                self.0 == 1 as $t
+            }
+        }
+        impl Applier<(Sum<$t>, Size)> for Times<$t> {
+            #[inline]
+            fn apply(&self, (Sum(s), n): (Sum<$t>, Size)) -> (Sum<$t>, Size) {
+                (Sum(s * self.0), n)
+            }
+        }
+        impl Applier<Counted<$t>> for Times<$t> {
+            #[inline]
+            fn apply(&self, to: Counted<$t>) -> Counted<$t> {
+                Counted { value: to.value * self.0, ..to }
+            }
+        }
+        impl Applier<Counted<$t>> for Plus<$t> {
+            #[inline]
+            fn apply(&self, to: Counted<$t>) -> Counted<$t> {
+                Counted { value: to.value + self.0 * to.count as $t, ..to }
+            }
+        }
+        impl Applier<Counted<$t>> for Affine<$t> {
+            #[inline]
+            fn apply(&self, to: Counted<$t>) -> Counted<$t> {
+                Counted { value: self.a * to.value + self.b * to.count as $t, ..to }
+            }
+        }
+        )*
+    };
+}
+
+impl_counted![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize, f32, f64];
+
+/// Xor a constant into every element, applies to `(Xor<T>, Size)`
+///
+/// Xor-ing `c` into `n` elements changes their total xor by `c`
+/// only when `n` is odd, that's why the [`Size`] is needed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XorWith<T>(pub T);
+
+macro_rules! impl_xor_with {
+    [$($t:ty),*] => {
+        $(
+        impl Semigroup for XorWith<$t> {
+            #[inline]
+            fn merge(self, other: Self) -> Self {
+                XorWith(self.0 ^ other.0)
+            }
+        }
+        impl CommutativeSemigroup for XorWith<$t> {}
+        impl Monoid for XorWith<$t> {
+            #[inline]
+            fn empty() -> Self {
+                XorWith(0)
+            }
+
+            #[inline]
+            fn is_identity(&self) -> bool {
+                self.0 == 0
+            }
+        }
+        impl Group for XorWith<$t> {
+            #[inline]
+            fn inverse(self) -> Self {
+                self
+            }
+        }
+        impl Applier<(Xor<$t>, Size)> for XorWith<$t> {
+            #[inline]
+            fn apply(&self, (Xor(x), n): (Xor<$t>, Size)) -> (Xor<$t>, Size) {
+                if n.0 % 2 == 1 {
+                    (Xor(x ^ self.0), n)
+                } else {
+                    (Xor(x), n)
+                }
+            }
+        }
+        )*
+    };
+}
+
+impl_xor_with![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize];
+
+/// The shipped modifiers apply to `Option<V>` as they do to `V`, leaving `None` alone
+macro_rules! impl_option_applier {
+    [$($m:ident),*] => {
+        $(
+        impl<V: Semigroup, T> Applier<Option<V>> for $m<T>
+        where
+            $m<T>: Applier<V>,
+        {
+            #[inline]
+            fn apply(&self, to: Option<V>) -> Option<V> {
+                to.map(|v| self.apply(v))
+            }
+        }
+        )*
+    };
+}
+
+impl_option_applier![Plus, Times, Affine, XorWith, Assign];
+
+/// Replace every element by a constant, `Assign(None)` keeps them as they are
+///
+/// The newer assignment wins, i.e. `new.merge(old)` is `new` unless it is `None`.
+/// Assigning `x` to `(Sum<T>, Size)` makes the sum `x * size`.
+///
+/// # Example
+///
+/// ```
+/// use solution::seg_tree::{monoids::*, SegTree};
+///
+/// let tree: SegTree<_, Assign<i64>> = (1..=5).map(|x| (Sum(x), Size(1))).collect();
+/// let tree = tree.apply(1..4, Assign(Some(7))).apply(3..5, Assign(Some(0)));
+/// assert_eq!(tree.query(..).0, Sum(1 + 7 + 7));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Assign<T>(pub Option<T>);
+
+macro_rules! impl_assign {
+    [$($t:ty),*] => {
+        $(
+        impl Applier<(Sum<$t>, Size)> for Assign<$t> {
+            #[inline]
+            fn apply(&self, (s, n): (Sum<$t>, Size)) -> (Sum<$t>, Size) {
+                match self.0 {
+                    Some(x) => (Sum(x * n.0 as $t), n),
+                    None => (s, n),
+                }
+            }
+        }
+        )*
+    };
+}
+
+impl_assign![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize, f32, f64];
+
+impl<T> Semigroup for Assign<T> {
+    #[inline]
+    fn merge(self, other: Self) -> Self {
+        Assign(self.0.or(other.0))
+    }
+}
+
+impl<T> Idempotent for Assign<T> {}
+
+impl<T> Monoid for Assign<T> {
+    #[inline]
+    fn empty() -> Self {
+        Assign(None)
+    }
+
+    #[inline]
+    fn is_identity(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+/// The modifier `M` together with the reversals of [`SegTree::reverse`](super::SegTree::reverse)
+///
+/// A `SegTree<V, Flip<M>>` applies the modifiers of `M` wrapped by
+/// [`Flip::from`], and can also reverse ranges. A reversal is pending in
+/// a `Flip` like any modifier, and it is pushed down by swapping the
+/// children of a branch and the sides of its value, see [`Reversible`].
+/// Only `SegTree::reverse` makes a `Flip` that reverses, as one applied to
+/// a range would reverse each subtree covering that range on its own.
+///
+/// `M` has to commute with the mirroring:
+/// `m.apply(v).swap_sides() == m.apply(v.swap_sides())`.
+///
+/// # Example
+///
+/// ```
+/// use solution::seg_tree::{monoids::*, SegTree};
+///
+/// let tree: SegTree<_, Flip<Plus<i64>>> = (1..=5).map(|x| (Sum(x), Size(1))).collect();
+/// let tree = tree.reverse(1..4).apply(..2, Flip::from(Plus(10)));
+/// assert_eq!(tree.get(1), (Sum(14), Size(1)));
+/// assert_eq!(tree.query(2..).0, Sum(3 + 2 + 5));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Flip<M> {
+    reversed: bool,
+    modifier: M,
+}
+
+impl<M: Monoid> Flip<M> {
+    /// Just a reversal, for [`SegTree::reverse`](super::SegTree::reverse)
+    pub(super) fn reversal() -> Self {
+        Flip {
+            reversed: true,
+            modifier: M::empty(),
+        }
+    }
+}
+
+impl<M> Flip<M> {
+    /// The modifier applied besides the reversal
+    pub fn modifier(&self) -> &M {
+        &self.modifier
+    }
+}
+
+/// `m` without reversing, to be applied to a `SegTree<V, Flip<M>>`
+impl<M> From<M> for Flip<M> {
+    fn from(modifier: M) -> Self {
+        Flip {
+            reversed: false,
+            modifier,
+        }
+    }
+}
+
+impl<M: Semigroup> Semigroup for Flip<M> {
+    #[inline]
+    fn merge(self, other: Self) -> Self {
+        Flip {
+            reversed: self.reversed != other.reversed,
+            modifier: M::merge(self.modifier, other.modifier),
+        }
+    }
+}
+
+impl<M: Monoid> Monoid for Flip<M> {
+    #[inline]
+    fn empty() -> Self {
+        Flip::from(M::empty())
+    }
+
+    #[inline]
+    fn is_identity(&self) -> bool {
+        !self.reversed && self.modifier.is_identity()
+    }
+}
+
+impl<V: Reversible, M: Applier<V>> Applier<V> for Flip<M> {
+    #[inline]
+    fn apply(&self, to: V) -> V {
+        let v = self.modifier.apply(to);
+        if self.reversed {
+            v.swap_sides()
+        } else {
+            v
+        }
+    }
+
+    #[inline]
+    fn reverses(&self) -> bool {
+        self.reversed
+    }
+}
+
+/// Modifiers show what they do to an element, e.g. `+3`, `2x+1` or `=5`
+impl fmt::Display for NoOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("id")
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Plus<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "+{}", self.0)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Times<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "*{}", self.0)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Affine<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x+{}", self.a, self.b)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for XorWith<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "^{}", self.0)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Assign<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(x) => write!(f, "={}", x),
+            None => f.write_str("id"),
+        }
+    }
+}
+
+impl<M: fmt::Display> fmt::Display for Flip<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.reversed {
+            write!(f, "rev {}", self.modifier)
+        } else {
+            self.modifier.fmt(f)
+        }
+    }
+}
+
+/// The maximum sum of a non-empty subarray, for the "GSS" problems
+///
+/// Besides the `best` subarray, each range keeps its total `sum`, its best
+/// `prefix` and `suffix`, which are what a subarray across two ranges is
+/// made of, and its `len` so that the identity (with `len == 0`) has no
+/// subarray at all. Start each element from [`MaxSubarray::new`].
+///
+/// # Example
+///
+/// ```
+/// use solution::seg_tree::{monoids::*, SegTree};
+///
+/// let tree: SegTree<_, Assign<i64>> = [2, -5, 3, -1, 4, -9, 1].into_iter().map(MaxSubarray::new).collect();
+/// assert_eq!(tree.query(..).best, 3 - 1 + 4);
+/// assert_eq!(tree.query(5..6).best, -9);
+/// assert_eq!(tree.apply(3..4, Assign(Some(-10))).query(..).best, 4);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaxSubarray<T> {
+    pub sum: T,
+    pub prefix: T,
+    pub suffix: T,
+    pub best: T,
+    pub len: usize,
+}
+
+impl<T: Copy> MaxSubarray<T> {
+    /// A single element `x`
+    #[inline]
+    pub fn new(x: T) -> Self {
+        MaxSubarray {
+            sum: x,
+            prefix: x,
+            suffix: x,
+            best: x,
+            len: 1,
+        }
+    }
+}
+
+/// `max` for `PartialOrd`, so that floats work as well
+#[inline]
+fn larger<T: PartialOrd>(a: T, b: T) -> T {
+    if a < b {
+        b
+    } else {
+        a
+    }
+}
+
+macro_rules! impl_max_subarray {
+    [$($t:ty),*] => {
+        $(
+        impl Semigroup for MaxSubarray<$t> {
+            fn merge(self, other: Self) -> Self {
+                if self.len == 0 {
+                    return other;
+                }
+                if other.len == 0 {
+                    return self;
+                }
+                MaxSubarray {
+                    sum: self.sum + other.sum,
+                    prefix: larger(self.prefix, self.sum + other.prefix),
+                    suffix: larger(other.suffix, self.suffix + other.sum),
+                    best: larger(larger(self.best, other.best), self.suffix + other.prefix),
+                    len: self.len + other.len,
+                }
+            }
+        }
+        impl Monoid for MaxSubarray<$t> {
+            #[inline]
+            fn empty() -> Self {
+                MaxSubarray {
+                    sum: 0 as $t,
+                    prefix: 0 as $t,
+                    suffix: 0 as $t,
+                    best: 0 as $t,
+                    len: 0,
+                }
+            }
+        }
+        impl Applier<MaxSubarray<$t>> for Assign<$t> {
+            fn apply(&self, to: MaxSubarray<$t>) -> MaxSubarray<$t> {
+                match self.0 {
+                    Some(x) if to.len > 0 => {
+                        let sum = x * to.len as $t;
+                        // all of it if it is positive, or a single element
+                        let best = larger(sum, x);
+                        MaxSubarray {
+                            sum,
+                            prefix: best,
+                            suffix: best,
+                            best,
+                            len: to.len,
+                        }
+                    }
+                    _ => to,
+                }
+            }
+        }
+        impl Reversible for MaxSubarray<$t> {
+            #[inline]
+            fn swap_sides(self) -> Self {
+                MaxSubarray {
+                    prefix: self.suffix,
+                    suffix: self.prefix,
+                    ..self
+                }
+            }
+        }
+        )*
+    };
+}
+
+impl_max_subarray![i8, i16, i32, i64, i128, isize, f32, f64];
+
+/// The polynomial hash `s[0] * B^(n-1) + ... + s[n-1] * B^0` modulo `P`
+///
+/// Each range keeps `B^len` besides its hash, which is what merging needs:
+/// the hash of `a` then `b` is `a.hash * B^b.len + b.hash`. Start each
+/// element from [`RollingHash::new`]. `P` must be below `2^63` and the
+/// elements below `P`, and a prime `P` with a random `B` keeps collisions
+/// unlikely.
+///
+/// # Example
+///
+/// ```
+/// use solution::seg_tree::{monoids::*, SegTree};
+/// use solution::traits::Semigroup;
+///
+/// type Hash = RollingHash<131, 1_000_000_007>;
+///
+/// let tree: SegTree<_, Assign<u64>> = "abracadabra".bytes().map(|c| Hash::new(c as u64)).collect();
+/// assert_eq!(tree.query(0..4), tree.query(7..11));
+/// assert_ne!(tree.query(0..4), tree.query(1..5));
+/// // "abracadabra" -> "abrabadabra"
+/// let tree = tree.apply(4..5, Assign(Some(b'b' as u64)));
+/// assert_eq!(tree.query(0..5), tree.query(7..11).merge(Hash::new(b'b' as u64)));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RollingHash<const B: u64, const P: u64> {
+    pub hash: u64,
+    /// `B^len` modulo `P`
+    pub power: u64,
+    pub len: usize,
+}
+
+impl<const B: u64, const P: u64> RollingHash<B, P> {
+    /// A single element `c`
+    #[inline]
+    pub fn new(c: u64) -> Self {
+        RollingHash {
+            hash: c % P,
+            power: B % P,
+            len: 1,
+        }
+    }
+
+    /// `self` repeated `n` times, in O(log n)
+    pub fn repeat(self, mut n: usize) -> Self {
+        let (mut result, mut base) = (Self::empty(), self);
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.merge(base);
+            }
+            base = base.merge(base);
+            n >>= 1;
+        }
+        result
+    }
+}
+
+impl<const B: u64, const P: u64> Semigroup for RollingHash<B, P> {
+    #[inline]
+    fn merge(self, other: Self) -> Self {
+        let mul = |a: u64, b: u64| (a as u128 * b as u128 % P as u128) as u64;
+        RollingHash {
+            hash: (mul(self.hash, other.power) + other.hash) % P,
+            power: mul(self.power, other.power),
+            len: self.len + other.len,
+        }
+    }
+}
+
+impl<const B: u64, const P: u64> Monoid for RollingHash<B, P> {
+    #[inline]
+    fn empty() -> Self {
+        RollingHash {
+            hash: 0,
+            power: 1 % P,
+            len: 0,
+        }
+    }
+}
+
+impl<const B: u64, const P: u64> Applier<RollingHash<B, P>> for Assign<u64> {
+    fn apply(&self, to: RollingHash<B, P>) -> RollingHash<B, P> {
+        match self.0 {
+            Some(c) => RollingHash::new(c).repeat(to.len),
+            None => to,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use super::{Applier, SegTree};
+use crate::traits::Monoid;
+
+use core::ops::{Range, RangeInclusive};
+
+/// A [`SegTree`] addressed the way problem statements do, from `1` to `n`
+///
+/// Ranges are `l..=r` with both ends included and `1 <= l`, `r <= n`;
+/// `l..=l - 1` is empty. They are translated to `l - 1..r` on the wrapped
+/// tree, so there is no `- 1` to forget at every call site.
+///
+/// ```
+/// use solution::seg_tree::{monoids::*, OneIndexed, SegTree};
+///
+/// let tree: SegTree<_, Plus<i64>> = [3, 1, 4, 1, 5].iter().map(|&x| (Sum(x), Size(1))).collect();
+/// let tree = OneIndexed::new(tree).apply(2..=3, Plus(10));
+/// assert_eq!(tree.query(1..=2).0, Sum(14));
+/// assert_eq!(tree.query(5..=5).0, Sum(5));
+/// ```
+#[derive(Debug, Clone)]
+pub struct OneIndexed<V, M> {
+    tree: SegTree<V, M>,
+}
+
+impl<V, M> OneIndexed<V, M> {
+    pub fn new(tree: SegTree<V, M>) -> Self {
+        Self { tree }
+    }
+
+    /// The wrapped tree
+    pub fn tree(&self) -> &SegTree<V, M> {
+        &self.tree
+    }
+
+    pub fn into_inner(self) -> SegTree<V, M> {
+        self.tree
+    }
+}
+
+/// Translate `l..=r` counted from `1` to `l - 1..r` counted from `0`
+fn zero_based(range: RangeInclusive<usize>) -> Range<usize> {
+    let (l, r) = range.into_inner();
+    assert!(l != 0, "range 0..={} starts before index 1", r);
+    l - 1..r
+}
+
+impl<V: Monoid + Clone, M: Applier<V> + Monoid + Clone> OneIndexed<V, M> {
+    /// [`SegTree::query`] of the elements `l` to `r`, both included
+    ///
+    /// # Panics
+    ///
+    /// Panics if `l == 0`, `r` is beyond the size of the tree, or `l > r + 1`.
+    pub fn query(&self, range: RangeInclusive<usize>) -> V {
+        self.tree.query(zero_based(range))
+    }
+
+    /// [`SegTree::apply`] on the elements `l` to `r`, both included
+    ///
+    /// # Panics
+    ///
+    /// Panics if `l == 0`, `r` is beyond the size of the tree, or `l > r + 1`.
+    pub fn apply(&self, range: RangeInclusive<usize>, m: M) -> Self {
+        Self::new(self.tree.apply(zero_based(range), m))
+    }
+}
@@ -0,0 +1,91 @@
+use super::{Applier, SegTree};
+use crate::traits::Monoid;
+
+use alloc::{vec, vec::Vec};
+use core::ops::RangeBounds;
+
+/// A history of [`SegTree`] versions
+///
+/// Version `0` is the tree given to [`Versioned::new`], every [`Versioned::apply`]
+/// adds a new version on top of the current one. Rolling back to an old
+/// version and applying again forks the history, all versions are kept.
+/// Storing a version costs only the nodes it changed.
+#[derive(Debug, Clone)]
+pub struct Versioned<V, M> {
+    versions: Vec<SegTree<V, M>>,
+    current: usize,
+}
+
+impl<V, M> Versioned<V, M> {
+    pub fn new(base: SegTree<V, M>) -> Self {
+        Self {
+            versions: vec![base],
+            current: 0,
+        }
+    }
+
+    /// The number of versions, including the base one
+    pub fn len(&self) -> usize {
+        self.versions.len()
+    }
+
+    /// Always `false`, there is at least the base version
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The index of the version that the next [`Versioned::apply`] builds on
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// The tree of `version`
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such version.
+    pub fn at(&self, version: usize) -> &SegTree<V, M> {
+        assert!(
+            version < self.len(),
+            "version {} does not exist, there are {} versions",
+            version,
+            self.len()
+        );
+        &self.versions[version]
+    }
+
+    /// Make `version` the current one, later versions are kept
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such version.
+    pub fn rollback(&mut self, version: usize) {
+        self.at(version);
+        self.current = version;
+    }
+}
+
+impl<V: Monoid + Clone, M: Applier<V> + Monoid + Clone> Versioned<V, M> {
+    /// Apply `m` on `range` of the current version, returning the index of the new version
+    ///
+    /// The new version becomes the current one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond the size of the tree.
+    pub fn apply(&mut self, range: impl RangeBounds<usize>, m: M) -> usize {
+        let next = self.versions[self.current].apply(range, m);
+        self.versions.push(next);
+        self.current = self.versions.len() - 1;
+        self.current
+    }
+
+    /// Query `range` of `version`
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no such version, or the range is invalid as in [`SegTree::query`].
+    pub fn query_at(&self, version: usize, range: impl RangeBounds<usize>) -> V {
+        self.at(version).query(range)
+    }
+}
@@ -0,0 +1,120 @@
+use super::range::resolve_range;
+use super::seg_tree::{monoids::NoOp, SegTree};
+use crate::traits::*;
+
+use alloc::{vec, vec::Vec};
+use core::ops::{Range, RangeBounds};
+
+/// A segment tree of segment trees, for rectangle queries on a grid
+///
+/// The outer tree is over `x`, each of its nodes keeps a [`SegTree`] over `y`
+/// merging the columns it covers. Only point updates are supported, there is
+/// no `apply` as lazy propagation does not carry over to two dimensions.
+/// For the same reason there is no modifier parameter: the inner trees are
+/// fixed to [`NoOp`], a pending modifier could never be pushed into them.
+///
+/// The values have to be commutative, because a rectangle is merged from
+/// the blocks of the outer tree in no particular row-by-column order.
+#[derive(Clone, Debug)]
+pub struct SegTree2D<V> {
+    width: usize,
+    height: usize,
+    log: u32,
+    /// `nodes[1]` is the root, `nodes[k]` has children `2k` and `2k + 1`,
+    /// the columns start at `nodes[1 << log]`
+    nodes: Vec<SegTree<V, NoOp>>,
+}
+
+impl<V> SegTree2D<V> {
+    /// The size along `x`
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The size along `y`
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
+impl<V: CommutativeSemigroup + Monoid + Clone> SegTree2D<V> {
+    /// Build a `width` by `height` grid whose value at `(x, y)` is `init(x, y)`
+    pub fn build<F: Fn(usize, usize) -> V>(width: usize, height: usize, init: F) -> Self {
+        let log = width.next_power_of_two().trailing_zeros();
+        let leaves = 1 << log;
+        let mut nodes = vec![SegTree::Empty; leaves];
+        nodes.extend((0..leaves).map(|x| {
+            if x < width {
+                SegTree::build(height, |y| init(x, y))
+            } else {
+                SegTree::build(height, |_| V::empty())
+            }
+        }));
+        for k in (1..leaves).rev() {
+            nodes[k] = nodes[2 * k].pointwise_merge(&nodes[2 * k + 1]);
+        }
+        SegTree2D {
+            width,
+            height,
+            log,
+            nodes,
+        }
+    }
+
+    /// The merged value of the rectangle `xs` by `ys`
+    ///
+    /// # Panics
+    ///
+    /// Panics if either range is reversed or out of bounds.
+    pub fn query(&self, xs: impl RangeBounds<usize>, ys: impl RangeBounds<usize>) -> V {
+        let Range { start, end } = resolve_range(xs, self.width, "x axis");
+        let ys = resolve_range(ys, self.height, "y axis");
+        let (mut l, mut r) = (start + (1 << self.log), end + (1 << self.log));
+        let mut result = V::empty();
+        while l < r {
+            if l & 1 == 1 {
+                result = V::merge(result, self.nodes[l].query(ys.clone()));
+                l += 1;
+            }
+            if r & 1 == 1 {
+                r -= 1;
+                result = V::merge(result, self.nodes[r].query(ys.clone()));
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+        result
+    }
+
+    /// The value at `(x, y)`, `V::empty()` if it is out of bounds
+    pub fn get(&self, x: usize, y: usize) -> V {
+        if x < self.width {
+            self.nodes[x + (1 << self.log)].get(y)
+        } else {
+            V::empty()
+        }
+    }
+
+    /// Replace the value at `(x, y)` in O(log(width) log(height))
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` is out of bounds.
+    pub fn set(&mut self, x: usize, y: usize, value: V) {
+        assert!(
+            x < self.width && y < self.height,
+            "({}, {}) out of bounds for grid of size {}x{}",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        let mut k = x + (1 << self.log);
+        self.nodes[k] = self.nodes[k].set(y, value);
+        while k > 1 {
+            k >>= 1;
+            let merged = V::merge(self.nodes[2 * k].get(y), self.nodes[2 * k + 1].get(y));
+            self.nodes[k] = self.nodes[k].set(y, merged);
+        }
+    }
+}
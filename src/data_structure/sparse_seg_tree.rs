@@ -0,0 +1,203 @@
+use super::seg_tree::Applier;
+use crate::traits::Monoid;
+
+use alloc::rc::Rc;
+use core::ops::{Range, RangeBounds};
+
+/// A persistent lazy segment tree over a huge domain `0..len` of `u64` indices
+///
+/// Only the nodes on the paths of the updates are allocated, so `len` may be
+/// as large as `u64` allows, e.g. `10^18`, at a cost of O(log len) nodes per
+/// [`SparseSegTree::apply`]. Untouched ranges of `n` elements are worth
+/// `blank(n)`, which is `V::empty()` for [`SparseSegTree::new`]; values that
+/// know their length use [`SparseSegTree::with_blank`], e.g.
+/// `|n| (Sum(0), Size(n as usize))`.
+///
+/// Like [`SegTree`](super::seg_tree::SegTree), updates return a new tree
+/// and cloning is cheap.
+#[derive(Clone, Debug)]
+pub struct SparseSegTree<V, M> {
+    len: u64,
+    blank: fn(u64) -> V,
+    root: Option<Rc<Node<V, M>>>,
+}
+
+/// A touched range, `None` children are still blank
+#[derive(Debug)]
+struct Node<V, M> {
+    /// The merged value of the range, with `modifier` applied
+    value: V,
+    /// Pending for the children
+    modifier: M,
+    left: Option<Rc<Node<V, M>>>,
+    right: Option<Rc<Node<V, M>>>,
+}
+
+impl<V: Monoid, M> SparseSegTree<V, M> {
+    /// A tree of `len` elements, all worth `V::empty()`
+    pub fn new(len: u64) -> Self {
+        Self::with_blank(len, |_| V::empty())
+    }
+}
+
+impl<V, M> SparseSegTree<V, M> {
+    /// A tree of `len` elements where `n` untouched ones are worth `blank(n)`
+    ///
+    /// `blank(a + b)` has to be `blank(a).merge(blank(b))`.
+    pub fn with_blank(len: u64, blank: fn(u64) -> V) -> Self {
+        SparseSegTree {
+            len,
+            blank,
+            root: None,
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of allocated nodes, shared ones are counted once per path
+    pub fn node_count(&self) -> usize {
+        fn count<V, M>(node: &Option<Rc<Node<V, M>>>) -> usize {
+            node.as_ref()
+                .map_or(0, |node| 1 + count(&node.left) + count(&node.right))
+        }
+        count(&self.root)
+    }
+
+    /// Normalize `range` to a half-open `start..end` within `0..len`
+    fn resolve_range(&self, range: impl RangeBounds<u64>) -> Range<u64> {
+        super::range::resolve_range(range, self.len, "tree")
+    }
+}
+
+impl<V: Monoid + Clone, M: Applier<V> + Monoid + Clone> SparseSegTree<V, M> {
+    /// Query the merged value of `range` in O(log len)
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.len()`.
+    pub fn query(&self, range: impl RangeBounds<u64>) -> V {
+        let range = self.resolve_range(range);
+        if range.is_empty() {
+            return V::empty();
+        }
+        self.query_inner(&self.root, 0..self.len, range)
+    }
+
+    /// `range` is within `span`, the part of the domain `node` covers
+    fn query_inner(&self, node: &Option<Rc<Node<V, M>>>, span: Range<u64>, range: Range<u64>) -> V {
+        let node = match node {
+            None => return (self.blank)(range.end - range.start),
+            Some(node) => node,
+        };
+        if range == span {
+            return node.value.clone();
+        }
+        let mid = span.start + (span.end - span.start) / 2;
+        node.modifier.apply(if range.end <= mid {
+            self.query_inner(&node.left, span.start..mid, range)
+        } else if mid <= range.start {
+            self.query_inner(&node.right, mid..span.end, range)
+        } else {
+            V::merge(
+                self.query_inner(&node.left, span.start..mid, range.start..mid),
+                self.query_inner(&node.right, mid..span.end, mid..range.end),
+            )
+        })
+    }
+
+    /// Apply `m` on `range` in O(log len), returning the new tree
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.len()`.
+    pub fn apply(&self, range: impl RangeBounds<u64>, m: M) -> Self {
+        let range = self.resolve_range(range);
+        if range.is_empty() || m.is_identity() {
+            return self.clone();
+        }
+        SparseSegTree {
+            len: self.len,
+            blank: self.blank,
+            root: Some(self.apply_inner(&self.root, 0..self.len, range, m)),
+        }
+    }
+
+    /// The value of `node` covering `len` elements
+    fn value(&self, node: &Option<Rc<Node<V, M>>>, len: u64) -> V {
+        match node {
+            None => (self.blank)(len),
+            Some(node) => node.value.clone(),
+        }
+    }
+
+    fn apply_all(&self, node: &Option<Rc<Node<V, M>>>, len: u64, m: M) -> Rc<Node<V, M>> {
+        Rc::new(match node {
+            None => Node {
+                value: m.apply((self.blank)(len)),
+                modifier: m,
+                left: None,
+                right: None,
+            },
+            Some(node) => Node {
+                value: m.apply(node.value.clone()),
+                modifier: M::merge(m, node.modifier.clone()),
+                left: node.left.clone(),
+                right: node.right.clone(),
+            },
+        })
+    }
+
+    /// `range` is a non-empty part of `span`, the part of the domain `node` covers
+    fn apply_inner(
+        &self,
+        node: &Option<Rc<Node<V, M>>>,
+        span: Range<u64>,
+        range: Range<u64>,
+        m: M,
+    ) -> Rc<Node<V, M>> {
+        if range == span {
+            return self.apply_all(node, span.end - span.start, m);
+        }
+        let mid = span.start + (span.end - span.start) / 2;
+        let (mut left, mut right) = (None, None);
+        if let Some(node) = node {
+            // push down, unless there is nothing to push
+            let push = |child: &Option<Rc<Node<V, M>>>, len| {
+                if node.modifier.is_identity() {
+                    child.clone()
+                } else {
+                    Some(self.apply_all(child, len, node.modifier.clone()))
+                }
+            };
+            left = push(&node.left, mid - span.start);
+            right = push(&node.right, span.end - mid);
+        }
+
+        let (left_span, right_span) = (span.start..mid, mid..span.end);
+        let (left, right) = if range.end <= mid {
+            (Some(self.apply_inner(&left, left_span, range, m)), right)
+        } else if mid <= range.start {
+            (left, Some(self.apply_inner(&right, right_span, range, m)))
+        } else {
+            (
+                Some(self.apply_inner(&left, left_span, range.start..mid, m.clone())),
+                Some(self.apply_inner(&right, right_span, mid..range.end, m)),
+            )
+        };
+        Rc::new(Node {
+            value: V::merge(
+                self.value(&left, mid - span.start),
+                self.value(&right, span.end - mid),
+            ),
+            modifier: M::empty(),
+            left,
+            right,
+        })
+    }
+}
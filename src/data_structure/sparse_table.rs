@@ -0,0 +1,74 @@
+use crate::traits::*;
+
+use alloc::{vec, vec::Vec};
+use core::ops::{Range, RangeBounds};
+
+/// Static range queries in O(1) after an O(n log n) build
+///
+/// It is read-only, there is no `apply`, use a `SegTree` for that.
+/// The values have to be [`Idempotent`] as a query merges two
+/// overlapping ranges, e.g. `Max`, `Min` or `Gcd`.
+#[derive(Clone, Debug)]
+pub struct SparseTable<V> {
+    /// `table[k][i]` is the merged value of `i..i + 2^k`
+    table: Vec<Vec<V>>,
+}
+
+impl<V> SparseTable<V> {
+    pub fn size(&self) -> usize {
+        self.table.first().map_or(0, Vec::len)
+    }
+
+    fn resolve_range(&self, range: impl RangeBounds<usize>) -> Range<usize> {
+        super::range::resolve_range(range, self.size(), "table")
+    }
+}
+
+impl<V: Idempotent + Monoid + Clone> SparseTable<V> {
+    /// Query the merged value of a range, e.g. `start..end`, `..=end` or `..`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or ends beyond `self.size()`.
+    pub fn query(&self, range: impl RangeBounds<usize>) -> V {
+        let Range { start, end } = self.resolve_range(range);
+        if start == end {
+            return V::empty();
+        }
+        let k = (end - start).ilog2() as usize;
+        V::merge(
+            self.table[k][start].clone(),
+            self.table[k][end - (1 << k)].clone(),
+        )
+    }
+}
+
+impl<V: Idempotent + Clone> From<Vec<V>> for SparseTable<V> {
+    fn from(values: Vec<V>) -> Self {
+        let mut table = vec![values];
+        let mut width = 1;
+        while width * 2 <= table[0].len() {
+            let last = table.last().unwrap();
+            let next = (0..last.len() - width)
+                .map(|i| V::merge(last[i].clone(), last[i + width].clone()))
+                .collect();
+            table.push(next);
+            width *= 2;
+        }
+        SparseTable { table }
+    }
+}
+
+impl<V: Idempotent + Clone> From<&[V]> for SparseTable<V> {
+    #[inline]
+    fn from(values: &[V]) -> Self {
+        Self::from(values.to_vec())
+    }
+}
+
+impl<V: Idempotent + Clone> core::iter::FromIterator<V> for SparseTable<V> {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = V>>(iter: T) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
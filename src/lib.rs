@@ -1,9 +1,14 @@
-#![feature(test)]
+#![cfg_attr(test, feature(test))]
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+
+extern crate alloc;
 
 pub mod traits;
 
 pub mod data_structure;
 pub use data_structure::seg_tree;
 
+pub mod prelude;
+
 #[cfg(test)]
 pub mod test;
@@ -0,0 +1,17 @@
+//! The common toolkit in one import
+//!
+//! ```
+//! use solution::prelude::*;
+//!
+//! let tree: SegTree<(Sum<i64>, Size), Affine<i64>> = (1..=5).map(|x| (Sum(x), Size(1))).collect();
+//! let tree = tree.apply(1..4, Affine { a: 2, b: 1 });
+//! assert_eq!(tree.query(..), (Sum(1 + 5 + 7 + 9 + 5), Size(5)));
+//! ```
+
+pub use crate::seg_tree::monoids::*;
+pub use crate::seg_tree::{
+    Applier, ArcRef, Count, OneIndexed, RcRef, Reversible, SegTree, SharedRef,
+};
+pub use crate::traits::foldable::Foldable;
+pub use crate::traits::semigroup::Product;
+pub use crate::traits::{CommutativeSemigroup, Group, Idempotent, Monoid, Semigroup};
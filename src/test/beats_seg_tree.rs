@@ -0,0 +1,62 @@
+use super::Rng;
+use crate::data_structure::beats_seg_tree::*;
+use crate::seg_tree::monoids::{Max, Min};
+
+#[test]
+fn against_brute_force() {
+    let mut rng = Rng(0x9E3779B97F4A7C15);
+    for len in [0, 1, 2, 3, 7, 8, 13, 64] {
+        let mut values: Vec<i64> = (0..len).map(|_| rng.below(100) as i64 - 50).collect();
+        let mut tree = BeatsSegTree::build(len, |i| values[i]);
+        assert_eq!(tree.size(), len);
+        for _ in 0..500 {
+            let l = rng.below(len + 1);
+            let r = l + rng.below(len - l + 1);
+            let x = rng.below(100) as i64 - 50;
+            match rng.below(4) {
+                0 => {
+                    tree.chmin(l..r, x);
+                    for v in &mut values[l..r] {
+                        *v = (*v).min(x);
+                    }
+                }
+                1 => {
+                    tree.chmax(l..r, x);
+                    for v in &mut values[l..r] {
+                        *v = (*v).max(x);
+                    }
+                }
+                _ => {
+                    let range = &values[l..r];
+                    assert_eq!(tree.query_sum(l..r), range.iter().sum::<i64>());
+                    assert_eq!(
+                        tree.query_max(l..r),
+                        range.iter().max().map_or(Max::NegInf, |&v| Max::Has(v))
+                    );
+                    assert_eq!(
+                        tree.query_min(l..r),
+                        range.iter().min().map_or(Min::Inf, |&v| Min::Has(v))
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn chmin_chmax() {
+    let mut tree = BeatsSegTree::build(6, |i| [5, 1, 8, 3, 8, 2][i]);
+    tree.chmin(.., 4);
+    assert_eq!(tree.query_sum(..), 4 + 1 + 4 + 3 + 4 + 2);
+    tree.chmax(1..5, 3);
+    assert_eq!(tree.query_sum(..), 4 + 3 + 4 + 3 + 4 + 2);
+    assert_eq!(tree.query_max(5..), Max::Has(2));
+    assert_eq!(tree.query_min(..5), Min::Has(3));
+    assert_eq!(tree.query_max(2..2), Max::NegInf);
+}
+
+#[test]
+#[should_panic(expected = "range end 7 out of bounds for tree of size 6")]
+fn out_of_bounds() {
+    BeatsSegTree::build(6, |_| 0).chmin(..7, 0);
+}
@@ -0,0 +1,57 @@
+use crate::data_structure::fenwick::*;
+use crate::seg_tree::{monoids::*, SegTree};
+
+#[test]
+fn add_and_query() {
+    let mut rng = super::Rng(97);
+    let mut values: Vec<i64> = (0..37).map(|_| rng.below(100) as i64).collect();
+    let mut fenwick: Fenwick<_> = values.iter().map(|&v| Sum(v)).collect();
+    assert_eq!(fenwick.size(), values.len());
+    for _ in 0..200 {
+        let (i, delta) = (rng.below(values.len()), rng.below(100) as i64 - 50);
+        fenwick.add(i, Sum(delta));
+        values[i] += delta;
+        let n = rng.below(values.len() + 1);
+        assert_eq!(fenwick.prefix_sum(n), Sum(values[..n].iter().sum()));
+    }
+    for l in 0..=values.len() {
+        for r in l..=values.len() {
+            assert_eq!(fenwick.query(l..r), Sum(values[l..r].iter().sum()));
+        }
+    }
+    assert_eq!(
+        fenwick.to_vec(),
+        values.iter().map(|&v| Sum(v)).collect::<Vec<_>>()
+    );
+    assert_eq!(Fenwick::<Sum<i64>>::new(0).query(..), Sum(0));
+}
+
+#[test]
+fn xor() {
+    let mut fenwick = Fenwick::new(5);
+    fenwick.add(1, Xor(0b101u32));
+    fenwick.add(3, Xor(0b011));
+    fenwick.add(1, Xor(0b001));
+    assert_eq!(fenwick.query(1..=3), Xor(0b111));
+    assert_eq!(fenwick.query(2..), Xor(0b011));
+}
+
+#[test]
+fn seg_tree_round_trip() {
+    let tree: SegTree<Sum<i64>, NoOp> = [3, 1, 4, 1, 5, 9, 2].iter().map(|&v| Sum(v)).collect();
+    let fenwick = Fenwick::from_seg_tree(&tree);
+    assert_eq!(fenwick.query(2..6), tree.query(2..6));
+    assert_eq!(fenwick.to_seg_tree::<NoOp>(), tree);
+
+    // pending modifiers are applied on the way
+    let tree: SegTree<(Sum<i64>, Size), Plus<i64>> = (0..10).map(|v| (Sum(v), Size(1))).collect();
+    let tree = tree.apply(3..8, Plus(10));
+    let fenwick: Fenwick<Sum<i64>> = tree.iter().map(|(s, _)| s).collect();
+    assert_eq!(fenwick.query(..), tree.query(..).0);
+}
+
+#[test]
+#[should_panic(expected = "index 5 out of bounds for tree of size 5")]
+fn add_out_of_bounds() {
+    Fenwick::<Sum<i64>>::new(5).add(5, Sum(1));
+}
@@ -0,0 +1,72 @@
+use crate::seg_tree::monoids::*;
+use crate::traits::laws::*;
+use crate::traits::Semigroup;
+
+#[test]
+fn shipped_monoids() {
+    assert_monoid_laws(&[Sum(3i64), Sum(-7), Sum(0)]);
+    assert_monoid_laws(&[Max::Has(3), Max::Has(-1), Max::NegInf]);
+    assert_monoid_laws(&[Min::Has(3u8), Min::Has(0), Min::Inf]);
+    let floats = [1.5, -0.0, 0.0, f64::INFINITY, f64::NAN, -f64::NAN].map(TotalOrd);
+    assert_monoid_laws(&floats.map(Max::Has));
+    assert_monoid_laws(&floats.map(Min::Has));
+    assert_monoid_laws(&[Gcd(12u32), Gcd(18), Gcd(0), Gcd(7)]);
+    assert_monoid_laws(&[Single(Some('a')), Single(None), Single(Some('b'))]);
+    assert_monoid_laws(&[Some(Sum(1)), None, Some(Sum(-2))]);
+    assert_monoid_laws(&[(Sum(1), Size(1)), (Sum(5), Size(2))]);
+    assert_monoid_laws(&[Matrix([[1i64, 2], [3, 4]]), Matrix([[0, 1], [1, 0]])]);
+    assert_monoid_laws(&[
+        MinCount::new(3),
+        MinCount::new(1),
+        MinCount { min: 1, count: 2 },
+    ]);
+    assert_monoid_laws(&[-3, 5, 0, 2, -1].map(MaxSubarray::new));
+    assert_monoid_laws(&[
+        Counted::new(3),
+        Counted {
+            value: -2,
+            count: 4,
+        },
+    ]);
+    assert_monoid_laws(&[Plus(3), Plus(-1)]);
+    assert_monoid_laws(&[Times(3), Times(-1), Times(0)]);
+    assert_monoid_laws(&[
+        Affine { a: 2, b: 1 },
+        Affine { a: -1, b: 3 },
+        Affine { a: 0, b: 5 },
+    ]);
+    assert_monoid_laws(&[Assign(Some(3)), Assign(None), Assign(Some(-1))]);
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Minus(i32);
+
+impl Semigroup for Minus {
+    fn merge(self, other: Self) -> Self {
+        Minus(self.0 - other.0)
+    }
+}
+
+#[test]
+#[should_panic(expected = "merge is not associative for Minus(1), Minus(1), Minus(1)")]
+fn broken_associativity() {
+    assert_semigroup_laws(&[Minus(1), Minus(2)]);
+}
+
+#[test]
+#[should_panic(expected = "empty() is not an identity for Sum(2)")]
+fn broken_identity() {
+    #[derive(Clone, PartialEq, Debug)]
+    struct Sum(i32);
+    impl Semigroup for Sum {
+        fn merge(self, other: Self) -> Self {
+            Sum(self.0 + other.0)
+        }
+    }
+    impl crate::traits::Monoid for Sum {
+        fn empty() -> Self {
+            Sum(1)
+        }
+    }
+    assert_monoid_laws(&[Sum(2)]);
+}
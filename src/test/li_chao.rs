@@ -0,0 +1,42 @@
+use super::Rng;
+use crate::data_structure::li_chao::*;
+use crate::traits::semigroup::Min;
+
+#[test]
+fn against_brute_force() {
+    let mut rng = Rng(0xD1B54A32D192ED03);
+    for (lo, hi) in [(0, 0), (-5, 5), (-100, 37), (0, 1000)] {
+        let mut tree = LiChaoTree::new(lo..=hi);
+        let mut lines = vec![];
+        let mut versions = vec![(tree.clone(), lines.clone())];
+        for _ in 0..100 {
+            let (a, b) = (rng.below(41) as i64 - 20, rng.below(2001) as i64 - 1000);
+            tree = tree.add_line(a, b);
+            lines.push((a, b));
+            versions.push((tree.clone(), lines.clone()));
+        }
+        // every version still answers for its own lines
+        for (tree, lines) in &versions {
+            for x in lo..=hi {
+                let expected = lines.iter().map(|&(a, b)| Min::Has(a * x + b)).min();
+                assert_eq!(tree.query(x), expected.unwrap_or(Min::Inf));
+            }
+        }
+    }
+}
+
+#[test]
+fn huge_domain() {
+    let tree = LiChaoTree::new(i64::MIN / 4..=i64::MAX / 4)
+        .add_line(1, 0)
+        .add_line(-1, 10);
+    assert_eq!(tree.query(0), Min::Has(0));
+    assert_eq!(tree.query(100), Min::Has(-90));
+    assert_eq!(tree.query(-1_000_000_000_000), Min::Has(-1_000_000_000_000));
+}
+
+#[test]
+#[should_panic(expected = "x = 11 is outside of the domain 0..=10")]
+fn query_outside() {
+    LiChaoTree::new(0..=10).query(11);
+}
@@ -1 +1,28 @@
+pub mod beats_seg_tree;
+pub mod fenwick;
 pub mod fingertree;
+pub mod laws;
+pub mod li_chao;
+pub mod mut_seg_tree;
+pub mod persistent_array;
+pub mod seg_tree;
+pub mod seg_tree_2d;
+pub mod sparse_seg_tree;
+pub mod sparse_table;
+
+/// A tiny xorshift generator, good enough for randomized tests
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A random number in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
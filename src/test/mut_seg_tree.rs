@@ -0,0 +1,42 @@
+use super::Rng;
+use crate::data_structure::mut_seg_tree::*;
+use crate::seg_tree::{monoids::*, SegTree};
+
+#[test]
+fn against_persistent() {
+    let mut rng = Rng(0x2545F4914F6CDD1D);
+    for len in [0, 1, 2, 3, 7, 8, 13, 64] {
+        let init = |i: usize| (Sum(i as i64 * 7 % 11), Size(1));
+        let mut persistent: SegTree<_, Affine<i64>> = SegTree::build(len, init);
+        let mut tree: MutSegTree<_, Affine<i64>> = MutSegTree::build(len, init);
+        assert_eq!(tree.size(), len);
+        for _ in 0..200 {
+            let l = rng.below(len + 1);
+            let r = l + rng.below(len - l + 1);
+            if rng.below(2) == 0 {
+                let m = Affine {
+                    a: rng.below(3) as i64 - 1,
+                    b: rng.below(5) as i64,
+                };
+                persistent = persistent.apply(l..r, m);
+                tree.apply(l..r, m);
+            } else {
+                assert_eq!(tree.query(l..r), persistent.query(l..r));
+            }
+        }
+    }
+}
+
+#[test]
+fn rebuild() {
+    let mut tree: MutSegTree<_, Plus<i64>> = MutSegTree::with_capacity(10);
+    assert_eq!(tree.size(), 0);
+    assert_eq!(tree.query(..), (Sum(0), Size(0)));
+    for len in [10, 3, 0, 16, 7] {
+        tree.rebuild(len, |i| (Sum(i as i64), Size(1)));
+        assert_eq!(tree.size(), len);
+        tree.apply(len / 2.., Plus(1));
+        let expected = (0..len as i64).sum::<i64>() + (len - len / 2) as i64;
+        assert_eq!(tree.query(..), (Sum(expected), Size(len)));
+    }
+}
@@ -0,0 +1,28 @@
+use crate::data_structure::persistent_array::*;
+
+#[test]
+fn get_set() {
+    let base: PersistentArray<String> = ["a", "b", "c", "d", "e"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let updated = base.set(1, "x".to_string()).set(4, "y".to_string());
+    assert_eq!(
+        updated.iter().collect::<Vec<_>>(),
+        ["a", "x", "c", "d", "y"]
+    );
+    assert_eq!(base.iter().collect::<Vec<_>>(), ["a", "b", "c", "d", "e"]);
+    assert_eq!(updated.get(1).as_deref(), Some("x"));
+    assert_eq!(updated.get(5), None);
+    assert_eq!(updated.len(), 5);
+
+    let empty = PersistentArray::<u8>::from(vec![]);
+    assert!(empty.is_empty());
+    assert_eq!(empty.iter().count(), 0);
+}
+
+#[test]
+#[should_panic(expected = "index 3 out of bounds")]
+fn set_out_of_bounds() {
+    PersistentArray::from(vec![1, 2, 3]).set(3, 0);
+}
@@ -0,0 +1,2146 @@
+use crate::seg_tree::{monoids::*, *};
+use crate::traits::{Monoid, Semigroup};
+
+extern crate test;
+
+type Tree = SegTree<(Sum<i64>, Size), Plus<i64>>;
+
+fn build(values: &[i64]) -> Tree {
+    SegTree::build(values.len(), |i| (Sum(values[i]), Size::default()))
+}
+
+fn sum(tree: &Tree, l: usize, r: usize) -> i64 {
+    tree.query(l..r).0 .0
+}
+
+#[test]
+fn set() {
+    let tree = build(&[1, 2, 3, 4, 5]).apply(1..4, Plus(10));
+    let updated = tree.set(2, (Sum(100), Size(1)));
+
+    assert_eq!(sum(&updated, 0, 5), 1 + 12 + 100 + 14 + 5);
+    assert_eq!(sum(&updated, 2, 3), 100);
+    // the old version is untouched
+    assert_eq!(sum(&tree, 0, 5), 1 + 12 + 13 + 14 + 5);
+
+    // pending modifiers must not leak into the new value
+    let updated = tree.apply(0..5, Plus(1)).set(3, (Sum(7), Size(1)));
+    assert_eq!(sum(&updated, 3, 4), 7);
+    assert_eq!(sum(&updated, 0, 5), 2 + 13 + 14 + 7 + 6);
+}
+
+#[test]
+#[should_panic(expected = "out of bounds")]
+fn set_out_of_bounds() {
+    build(&[1, 2, 3]).set(3, (Sum(0), Size(1)));
+}
+
+#[test]
+fn apply_point() {
+    let tree = build(&[1, 2, 3, 4, 5]).apply(1..4, Plus(10));
+    for index in 0..5 {
+        let pointed = tree.apply_point(index, Plus(100));
+        let ranged = tree.apply(index..=index, Plus(100));
+        assert_eq!(pointed, ranged);
+        // following the single path allocates no more than the range version
+        assert!(
+            Tree::shared_node_count(&[&tree, &pointed])
+                <= Tree::shared_node_count(&[&tree, &ranged])
+        );
+    }
+    let plain = build(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    let pointed = plain.apply_point(5, Plus(1));
+    assert_eq!(Tree::shared_node_count(&[&plain, &pointed]), 15 + 4);
+    let affine: SegTree<_, Affine<i64>> = (1..=4).map(|v| (Sum(v), Size(1))).collect();
+    let affine = affine
+        .apply(.., Affine { a: 2, b: 0 })
+        .apply_point(2, Affine { a: 1, b: 1 });
+    // 2 * 3 + 1, the pending doubling is applied first
+    assert_eq!(affine.get(2).0, Sum(7));
+}
+
+#[test]
+#[should_panic(expected = "index 5 out of bounds for tree of size 5")]
+fn apply_point_out_of_bounds() {
+    build(&[1, 2, 3, 4, 5]).apply_point(5, Plus(1));
+}
+
+#[test]
+fn get() {
+    let values = [3, 1, 4, 1, 5, 9, 2];
+    let tree = build(&values).apply(2..6, Plus(10)).apply(0..3, Plus(-1));
+    let expected = [2, 0, 13, 11, 15, 19, 2];
+    for (i, &v) in expected.iter().enumerate() {
+        assert_eq!(tree.get(i).0 .0, v);
+    }
+    assert_eq!(tree.get(values.len()).0 .0, 0);
+}
+
+#[test]
+#[should_panic(expected = "range 5..2 is invalid")]
+fn query_reversed_range() {
+    let (start, end) = (5, 2);
+    build(&[0; 10]).query(start..end);
+}
+
+#[test]
+#[should_panic(expected = "range end 1000000 out of bounds for tree of size 10")]
+fn apply_out_of_bounds() {
+    build(&[0; 10]).apply(0..1_000_000, Plus(1));
+}
+
+#[test]
+fn build_with() {
+    let values = [3, 1, 4, 1, 5, 9, 2];
+    let ranges = std::cell::RefCell::new(vec![]);
+    let tree = Tree::build_with(
+        values.len(),
+        |i| (Sum(values[i]), Size(1)),
+        |range| {
+            ranges.borrow_mut().push(range.clone());
+            Plus(range.len() as i64)
+        },
+    );
+    let ranges = ranges.into_inner();
+    let covered: Vec<i64> = (0..values.len())
+        .map(|i| {
+            ranges
+                .iter()
+                .filter(|r| r.contains(&i))
+                .map(|r| r.len() as i64)
+                .sum()
+        })
+        .collect();
+    let expected: Vec<i64> = values.iter().zip(&covered).map(|(v, c)| v + c).collect();
+    assert_eq!(tree, build(&expected));
+    assert_eq!(ranges.len(), values.len() - 1);
+
+    // pushing the modifiers down does not change anything
+    assert_eq!(tree.set(3, tree.get(3)), tree);
+    assert_eq!(
+        Tree::build_with(0, |_| unreachable!(), |_| unreachable!()),
+        Tree::Empty
+    );
+}
+
+#[test]
+fn range_bounds() {
+    let tree = build(&[1, 2, 3, 4, 5])
+        .apply(..=1, Plus(10))
+        .apply(3.., Plus(100));
+    assert_eq!(tree.query(..).0 .0, 11 + 12 + 3 + 104 + 105);
+    assert_eq!(tree.query(1..=3).0 .0, 12 + 3 + 104);
+    assert_eq!(tree.query(..2).0 .0, 11 + 12);
+    assert_eq!(tree.query(4..).0 .0, 105);
+}
+
+#[test]
+fn collect() {
+    let tree: Tree = (1..=100).map(|i| (Sum(i), Size(1))).collect();
+    assert_eq!(tree.size(), 100);
+    assert_eq!(sum(&tree, 0, 100), 5050);
+
+    let empty: Tree = std::iter::empty().collect();
+    assert!(matches!(empty, SegTree::Empty));
+}
+
+#[test]
+fn from_values() {
+    let values: Vec<_> = [5, 3, 8].iter().map(|&v| (Sum(v), Size(1))).collect();
+    let from_slice = Tree::from(&values[..]);
+    let from_vec = Tree::from(values);
+    assert_eq!(sum(&from_slice, 0, 3), 16);
+    assert_eq!(sum(&from_vec, 1, 3), 11);
+}
+
+#[test]
+fn to_vec() {
+    let tree = build(&[1, 2, 3, 4, 5, 6])
+        .apply(1..5, Plus(1))
+        .apply(0..3, Plus(10))
+        .apply(2..6, Plus(100));
+    let values: Vec<_> = tree.to_vec().into_iter().map(|(s, _)| s.0).collect();
+    assert_eq!(values, [11, 13, 114, 105, 106, 106]);
+    assert_eq!(Tree::build(0, |_| unreachable!()).to_vec().len(), 0);
+}
+
+#[test]
+fn iter() {
+    let tree = build(&[1, 2, 3, 4, 5]).apply(1..4, Plus(10));
+    let prefix: Vec<_> = tree
+        .iter()
+        .map(|(s, _)| s.0)
+        .take_while(|&v| v < 14)
+        .collect();
+    assert_eq!(prefix, [1, 12, 13]);
+    assert_eq!(tree.iter().count(), tree.size());
+}
+
+#[test]
+fn map() {
+    let tree = build(&[3, 1, 4, 1, 5]).apply(1..4, Plus(10));
+    let max: SegTree<_, Plus<i64>> = tree.map(|(s, _)| Max::Has(s.0));
+    assert_eq!(max.to_vec(), [3, 11, 14, 11, 5].map(Max::Has));
+    assert_eq!(max.query(..3), Max::Has(14));
+    let doubled: Tree = tree.map(|(s, n)| (Sum(2 * s.0), n));
+    assert_eq!(doubled, build(&[6, 22, 28, 22, 10]));
+    assert_eq!(depth(&doubled), depth(&tree));
+}
+
+#[test]
+fn zip_with() {
+    let a = build(&[3, 1, 4, 1, 5]).apply(1..4, Plus(10));
+    let b = build(&[2, 7, 1, 8, 2]).apply(3.., Plus(-1));
+    let diff: Tree = a.zip_with(&b, |(x, n), (y, _)| (Sum(x.0 - y.0), n));
+    assert_eq!(diff, build(&[1, 4, 13, 4, 4]));
+    let max: SegTree<_, NoOp> = a.zip_with(&b, |(x, _), (y, _)| Max::Has(x.0 * y.0));
+    assert_eq!(max.query(..), Max::Has(77));
+}
+
+#[test]
+#[should_panic(expected = "cannot zip trees of different sizes")]
+fn zip_with_sizes() {
+    let _: Tree = build(&[1, 2]).zip_with(&build(&[1]), |a, _| a);
+}
+
+#[test]
+fn display() {
+    let tree: SegTree<_, Plus<i64>> = [3, 1, 4, 1, 5].iter().map(|&v| Max::Has(v)).collect();
+    assert_eq!(tree.apply(1..3, Plus(2)).to_string(), "[3, 3, 6, 1, 5]");
+    assert_eq!(SegTree::<Max<i64>, Plus<i64>>::Empty.to_string(), "[]");
+}
+
+#[test]
+fn eq() {
+    let applied = build(&[1, 2, 3, 4])
+        .apply(0..2, Plus(1))
+        .apply(1..4, Plus(2));
+    assert_eq!(applied, build(&[2, 5, 5, 6]));
+    assert_eq!(applied.set(3, (Sum(6), Size(1))), applied);
+    assert_ne!(applied, build(&[2, 5, 5, 7]));
+    assert_ne!(applied, build(&[2, 5, 5]));
+    assert_eq!(Tree::Empty, build(&[]));
+}
+
+#[test]
+fn split() {
+    let values = [3, 1, 4, 1, 5, 9, 2];
+    let tree = build(&values).apply(1..5, Plus(10)).apply(3..7, Plus(-2));
+    let expected = tree.to_vec();
+    for index in 0..=values.len() {
+        let (left, right) = tree.split(index);
+        assert_eq!(left.to_vec(), expected[..index]);
+        assert_eq!(right.to_vec(), expected[index..]);
+        // both halves stay usable on their own
+        assert_eq!(
+            sum(&left.apply(.., Plus(1)), 0, index),
+            sum(&tree, 0, index) + index as i64
+        );
+        assert_eq!(
+            sum(&right, 0, right.size()),
+            sum(&tree, index, values.len())
+        );
+    }
+    assert!(matches!(tree.split(0).0, SegTree::Empty));
+    assert!(matches!(tree.split(values.len()).1, SegTree::Empty));
+}
+
+#[test]
+fn split_concat_sharing() {
+    let tree = build(&[1; 16]);
+    let nodes = tree.node_count();
+    for index in 0..=16 {
+        let (left, right) = tree.split(index);
+        let split = Tree::shared_node_count(&[&tree, &left, &right]) - nodes;
+        let joined = left.concat(&right);
+        let concat = Tree::shared_node_count(&[&tree, &left, &right, &joined]) - nodes - split;
+        // only the cut path is rebuilt, plus the roots of the halves, and
+        // the subtrees next to it are shared however the halves are joined
+        assert!(
+            split <= depth(&tree) + 1,
+            "split at {}: {} nodes",
+            index,
+            split
+        );
+        assert!(
+            concat <= depth(&tree),
+            "concat at {}: {} nodes",
+            index,
+            concat
+        );
+    }
+    // the two halves of the root are copied out and join under a new node
+    let (left, right) = tree.split(8);
+    assert_eq!(
+        Tree::shared_node_count(&[&tree, &left.concat(&right)]),
+        nodes + 3
+    );
+}
+
+#[test]
+#[should_panic(expected = "index 4 out of bounds for tree of size 3")]
+fn split_out_of_bounds() {
+    build(&[1, 2, 3]).split(4);
+}
+
+fn depth<V, M>(tree: &SegTree<V, M>) -> usize {
+    match tree {
+        SegTree::Branch { left, right, .. } => 1 + depth(left).max(depth(right)),
+        _ => 0,
+    }
+}
+
+#[test]
+fn concat() {
+    let values = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3];
+    let tree = build(&values).apply(2..8, Plus(10));
+    let expected = tree.to_vec();
+    for index in 0..=values.len() {
+        let (left, right) = tree.split(index);
+        let joined = left.apply(.., Plus(1)).concat(&right);
+        assert_eq!(joined.size(), values.len());
+        for l in 0..values.len() {
+            for r in l..=values.len() {
+                let bumped = index.min(r).saturating_sub(l) as i64;
+                assert_eq!(sum(&joined, l, r), sum(&tree, l, r) + bumped);
+            }
+        }
+        assert_eq!(left.concat(&right).to_vec(), expected);
+    }
+}
+
+#[test]
+fn concat_balanced() {
+    let unit = |v| Tree::Unit((Sum(v), Size(1)));
+    let mut appended = Tree::Empty;
+    let mut prepended = Tree::Empty;
+    for i in 0..1000 {
+        appended = appended.concat(&unit(i));
+        prepended = unit(999 - i).concat(&prepended);
+    }
+    let expected: Vec<_> = (0..1000).map(|i| (Sum(i), Size(1))).collect();
+    for tree in [&appended, &prepended] {
+        assert_eq!(tree.to_vec(), expected);
+        // a weight balanced tree with ratio 3 is no deeper than log_{4/3}(n)
+        assert!(depth(tree) <= 25, "depth {} is too deep", depth(tree));
+    }
+
+    let small = build(&[1, 2]);
+    let big = build(&(0..500).collect::<Vec<_>>()).apply(100..400, Plus(1));
+    for tree in [small.concat(&big), big.concat(&small)] {
+        assert_eq!(sum(&tree, 0, 502), 3 + 499 * 250 + 300);
+        assert!(depth(&tree) <= 25, "depth {} is too deep", depth(&tree));
+    }
+}
+
+#[test]
+fn insert_remove() {
+    let mut values = vec![3, 1, 4];
+    let mut tree = build(&values).apply(.., Plus(0));
+    for (i, index) in [0, 4, 2, 0, 7, 3].into_iter().enumerate() {
+        let v = 10 * i as i64;
+        let index = index.min(values.len());
+        tree = tree.insert(index, (Sum(v), Size(1)));
+        values.insert(index, v);
+        assert_eq!(tree, build(&values));
+    }
+    // front and back
+    tree = tree.insert(0, (Sum(-1), Size(1)));
+    tree = tree.insert(tree.size(), (Sum(-2), Size(1)));
+    values.insert(0, -1);
+    values.push(-2);
+    assert_eq!(tree, build(&values));
+
+    tree = tree.apply(2..6, Plus(5));
+    for v in &mut values[2..6] {
+        *v += 5;
+    }
+    for index in [0, usize::MAX, 3, 3, 0] {
+        let index = index.min(values.len() - 1);
+        tree = tree.remove(index);
+        values.remove(index);
+        assert_eq!(tree, build(&values));
+    }
+    while tree.size() > 0 {
+        tree = tree.remove(tree.size() / 2);
+    }
+    assert!(matches!(tree, SegTree::Empty));
+}
+
+#[test]
+#[should_panic(expected = "index 3 out of bounds for tree of size 3")]
+fn remove_out_of_bounds() {
+    build(&[1, 2, 3]).remove(3);
+}
+
+#[test]
+fn pointwise_merge() {
+    let a = build(&[1, 2, 3, 4, 5, 6, 7]).apply(1..5, Plus(10));
+    let b = a.apply(3..7, Plus(-3)).set(0, (Sum(100), Size(1)));
+    let merged = a.pointwise_merge(&b);
+    let expected: Vec<_> = a
+        .iter()
+        .zip(b.iter())
+        .map(|(x, y)| Semigroup::merge(x, y))
+        .collect();
+    assert_eq!(merged.to_vec(), expected);
+    assert_eq!(merged.total(), Semigroup::merge(a.total(), b.total()));
+
+    // a differently shaped tree of the same size
+    let (left, right) = b.split(2);
+    let reshaped = right.concat(&left);
+    let merged = a.pointwise_merge(&reshaped);
+    for (i, v) in merged.iter().enumerate() {
+        assert_eq!(v, Semigroup::merge(a.get(i), reshaped.get(i)));
+    }
+}
+
+#[test]
+#[should_panic(expected = "cannot merge trees of different sizes")]
+fn pointwise_merge_sizes() {
+    build(&[1, 2, 3]).pointwise_merge(&build(&[1, 2]));
+}
+
+#[test]
+fn split_off() {
+    let values = [3, 1, 4, 1, 5, 9, 2, 6];
+    let original = build(&values).apply(2..7, Plus(10));
+    for index in 0..=values.len() {
+        let mut head = original.clone();
+        let tail = head.split_off(index);
+        assert_eq!(head.size(), index);
+        assert_eq!(tail.size(), values.len() - index);
+        assert_eq!(head.concat(&tail), original);
+    }
+}
+
+#[test]
+#[should_panic(expected = "out of bounds")]
+fn split_off_out_of_bounds() {
+    build(&[1, 2]).split_off(3);
+}
+
+#[test]
+fn extend() {
+    let item = |x| (Sum(x), Size(1));
+    let tree = Tree::Empty.extend([3, 1, 4].map(item));
+    assert_eq!(tree, build(&[3, 1, 4]));
+
+    let tree = tree.apply(.., Plus(1));
+    let extended = tree.extend((1..=100).map(item));
+    let expected: Vec<i64> = [4, 2, 5].into_iter().chain(1..=100).collect();
+    assert_eq!(extended, build(&expected));
+    assert!(depth(&extended) <= 2 * 7);
+    assert_eq!(extended.extend(core::iter::empty()), extended);
+    // the old tree is untouched
+    assert_eq!(tree, build(&[4, 2, 5]));
+}
+
+#[test]
+fn len() {
+    let tree = build(&[3, 1, 4]);
+    assert_eq!(tree.len(), 3);
+    assert!(!tree.is_empty());
+    assert!(Tree::Empty.is_empty());
+    assert!(tree.split(0).0.is_empty());
+    assert_eq!(tree.remove(0).remove(0).len(), 1);
+}
+
+#[test]
+fn default() {
+    #[derive(Default)]
+    struct State {
+        tree: Tree,
+        shared: ArcSegTree<Max<i64>, NoOp>,
+    }
+    let state = State::default();
+    assert_eq!(state.tree.size(), 0);
+    assert_eq!(state.tree, Tree::Empty);
+    assert_eq!(state.shared.query(..), Max::NegInf);
+}
+
+#[test]
+fn node_count() {
+    let tree = build(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(tree.node_count(), 15);
+    assert_eq!(Tree::Empty.node_count(), 0);
+    assert_eq!(Tree::shared_node_count(&[&tree]), 15);
+
+    // a point update copies the path to the leaf, the siblings along it are
+    // shared unless there is a pending modifier to push into them
+    let updated = tree.set(5, (Sum(0), Size(1)));
+    assert_eq!(updated.node_count(), 15);
+    assert_eq!(Tree::shared_node_count(&[&tree, &updated]), 15 + 4);
+    let pending = tree.apply(4..8, Plus(1));
+    let updated = pending.set(5, (Sum(0), Size(1)));
+    assert_eq!(updated.get(5), (Sum(0), Size(1)));
+    // the path and the two siblings below the pending modifier
+    assert_eq!(Tree::shared_node_count(&[&pending, &updated]), 15 + 4 + 2);
+    assert_eq!(Tree::shared_node_count(&[&tree, &tree.clone()]), 15 + 1);
+
+    // without pending modifiers, apply copies nothing but the path
+    let applied = tree.apply(5..6, Plus(1));
+    assert_eq!(Tree::shared_node_count(&[&tree, &applied]), 15 + 4);
+    // a pending modifier still has to be pushed into the siblings
+    let pending = tree.apply(4..8, Plus(1));
+    let applied = pending.apply(5..6, Plus(1));
+    assert_eq!(
+        Tree::shared_node_count(&[&pending, &applied]),
+        15 + 1 + 3 + 2
+    );
+}
+
+#[test]
+fn apply_identity() {
+    let tree = build(&[1, 2, 3, 4, 5, 6, 7, 8]).apply(2..6, Plus(1));
+    let same = tree.apply(1..7, Plus(0));
+    assert_eq!(same, tree);
+    // nothing but the root is copied
+    assert_eq!(
+        Tree::shared_node_count(&[&tree, &same]),
+        tree.node_count() + 1
+    );
+
+    assert!(Plus(0).is_identity() && !Plus(1).is_identity());
+    assert!(Affine { a: 1, b: 0 }.is_identity() && !Affine { a: 1, b: 2 }.is_identity());
+    assert!((NoOp, XorWith(0u8)).is_identity());
+    assert!(!Some(Plus(0)).is_identity());
+}
+
+#[test]
+#[should_panic(expected = "range end 9 out of bounds for tree of size 8")]
+fn apply_identity_out_of_bounds() {
+    build(&[0; 8]).apply(0..9, Plus(0));
+}
+
+#[test]
+fn total() {
+    let tree = build(&[1, 2, 3, 4, 5]).apply(1..4, Plus(10));
+    assert_eq!(tree.total().0 .0, 45);
+    assert_eq!(tree.total().0, tree.query(..).0);
+    assert_eq!(Tree::build(0, |_| unreachable!()).total().0 .0, 0);
+}
+
+#[test]
+fn fold_range() {
+    let values = [3, 1, 4, 1, 5, 9, 2, 6];
+    let tree = build(&values).apply(2..7, Plus(1));
+    for l in 0..=values.len() {
+        for r in l..=values.len() {
+            let (total, branches, leaves) = tree.fold_range(
+                l..r,
+                (0, 0, 0),
+                |(t, b, u), v| (t + v.0 .0, b + 1, u),
+                |(t, b, u), v| (t + v.0 .0, b, u + 1),
+            );
+            assert_eq!(total, sum(&tree, l, r));
+            assert!(branches + leaves <= 2 * 3);
+            assert_eq!(leaves > 0 || branches > 0, l < r);
+        }
+    }
+    let sizes = tree.fold_range(
+        1..7,
+        vec![],
+        |mut acc, v| {
+            acc.push(v.1 .0);
+            acc
+        },
+        |mut acc, v| {
+            acc.push(v.1 .0);
+            acc
+        },
+    );
+    assert_eq!(sizes, [1, 2, 2, 1]);
+}
+
+#[test]
+fn decompose() {
+    let tree = build(&[3, 1, 4, 1, 5, 9, 2, 6]).apply(0..5, Plus(2));
+    let pieces: Vec<_> = tree
+        .decompose(1..7)
+        .into_iter()
+        .map(|(s, n)| (s.0, n.0))
+        .collect();
+    assert_eq!(pieces, [(3, 1), (9, 2), (16, 2), (2, 1)]);
+    for l in 0..=tree.size() {
+        for r in l..=tree.size() {
+            let merged = tree
+                .decompose(l..r)
+                .into_iter()
+                .fold(Monoid::empty(), Semigroup::merge);
+            assert_eq!(merged, tree.query(l..r));
+        }
+    }
+}
+
+#[test]
+fn max_right() {
+    let values = [3, 1, 4, 1, 5, 9, 2, 6];
+    let tree = build(&values).apply(2..5, Plus(1));
+    for l in 0..=values.len() {
+        for limit in 0..40 {
+            let pred = |v: &(Sum<i64>, Size)| v.0 .0 <= limit;
+            let expected = (l..=values.len())
+                .take_while(|&r| pred(&tree.query(l..r)))
+                .last()
+                .unwrap();
+            assert_eq!(tree.max_right(l, pred), expected);
+        }
+    }
+}
+
+#[test]
+fn min_left() {
+    let values = [3, 1, 4, 1, 5, 9, 2, 6];
+    let tree = build(&values).apply(2..5, Plus(1));
+    for r in 0..=values.len() {
+        for limit in 0..40 {
+            let pred = |v: &(Sum<i64>, Size)| v.0 .0 <= limit;
+            let expected = (0..=r)
+                .rev()
+                .take_while(|&l| pred(&tree.query(l..r)))
+                .last()
+                .unwrap();
+            assert_eq!(tree.min_left(r, pred), expected);
+        }
+    }
+}
+
+#[test]
+fn max_min() {
+    let values = [3, 1, 4, 1, 5, 9, 2, 6];
+    let max: SegTree<_, Plus<i64>> = values.iter().map(|&v| Max::Has(v)).collect();
+    let min: SegTree<_, Plus<i64>> = values.iter().map(|&v| Min::Has(v)).collect();
+    let (max, min) = (max.apply(4..6, Plus(-5)), min.apply(0..4, Plus(5)));
+    assert_eq!(max.query(0..8), Max::Has(6));
+    assert_eq!(max.query(4..6), Max::Has(4));
+    assert_eq!(min.query(0..8), Min::Has(2));
+    assert_eq!(min.query(0..4), Min::Has(6));
+    assert_eq!(min.query(3..3), Min::Inf);
+}
+
+#[test]
+fn no_op() {
+    let tree: SegTree<_, NoOp> = [3, 1, 4, 1, 5].iter().map(|&v| Max::Has(v)).collect();
+    assert_eq!(tree.query(1..4), Max::Has(4));
+    assert_eq!(tree.apply(.., NoOp).to_vec(), tree.to_vec());
+}
+
+#[test]
+fn affine() {
+    let mut values = [5i64, -2, 7, 0, 3, 3, -8];
+    let mut tree: SegTree<_, Affine<i64>> = values.iter().map(|&v| (Sum(v), Size(1))).collect();
+    let updates = [(0..4, 2, 1), (2..7, -1, 3), (1..3, 3, -2), (0..7, 1, 5)];
+    for (range, a, b) in updates {
+        tree = tree.apply(range.clone(), Affine { a, b });
+        for v in &mut values[range] {
+            *v = a * *v + b;
+        }
+        for l in 0..values.len() {
+            for r in l..=values.len() {
+                assert_eq!(tree.query(l..r).0 .0, values[l..r].iter().sum::<i64>());
+            }
+        }
+    }
+}
+
+#[test]
+fn unapply() {
+    let tree = build(&[1, 2, 3, 4, 5]).apply(0..2, Plus(7));
+    let applied = tree.apply(1..4, Plus(3)).apply(2..5, Plus(-6));
+    let undone = applied.unapply(1..4, Plus(3));
+    assert_eq!(undone.to_vec(), tree.apply(2..5, Plus(-6)).to_vec());
+    assert_eq!(undone.unapply(2..5, Plus(-6)).to_vec(), tree.to_vec());
+}
+
+#[test]
+fn xor_with() {
+    let mut values = [0b1010u32, 0b0110, 0b1111, 0b0001, 0b1000, 0b0011];
+    let mut tree: SegTree<_, XorWith<u32>> = values.iter().map(|&v| (Xor(v), Size(1))).collect();
+    // odd and even lengths, overlapping each other
+    for (range, c) in [
+        (0..3, 0b0101),
+        (1..5, 0b1100),
+        (2..4, 0b0111),
+        (0..6, 0b1001),
+    ] {
+        tree = tree.apply(range.clone(), XorWith(c));
+        for v in &mut values[range] {
+            *v ^= c;
+        }
+        for l in 0..values.len() {
+            for r in l..=values.len() {
+                let expected = values[l..r].iter().fold(0, |acc, v| acc ^ v);
+                assert_eq!(tree.query(l..r).0, Xor(expected));
+            }
+        }
+    }
+    let undone = tree
+        .apply(1..5, XorWith(0b0110))
+        .unapply(1..5, XorWith(0b0110));
+    assert_eq!(undone.to_vec(), tree.to_vec());
+}
+
+#[test]
+fn gcd_lcm() {
+    let values = [12u64, 18, 0, 30, 7, 14];
+    let gcd: SegTree<_, NoOp> = values.iter().map(|&v| Gcd(v)).collect();
+    let lcm: SegTree<_, NoOp> = values.iter().map(|&v| Lcm(v)).collect();
+    assert_eq!(gcd.query(0..2), Gcd(6));
+    assert_eq!(gcd.query(0..4), Gcd(6));
+    assert_eq!(gcd.query(2..3), Gcd(0));
+    assert_eq!(gcd.query(3..5), Gcd(1));
+    assert_eq!(gcd.query(4..4), Gcd(0));
+    assert_eq!(lcm.query(0..2), Lcm(36));
+    assert_eq!(lcm.query(4..6), Lcm(14));
+    assert_eq!(lcm.query(1..4), Lcm(0));
+    assert_eq!(lcm.query(3..3), Lcm(1));
+}
+
+/// Strings merged by concatenation, the most visible non-commutative monoid
+#[derive(Clone, PartialEq, Debug)]
+struct Concat(String);
+
+impl Semigroup for Concat {
+    fn merge(self, other: Self) -> Self {
+        Concat(self.0 + &other.0)
+    }
+}
+
+impl Monoid for Concat {
+    fn empty() -> Self {
+        Concat(String::new())
+    }
+}
+
+/// Rotate the lowercase letters, so that pending modifiers are involved too
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Rotate(u8);
+
+impl Semigroup for Rotate {
+    fn merge(self, other: Self) -> Self {
+        Rotate((self.0 + other.0) % 26)
+    }
+}
+
+impl Monoid for Rotate {
+    fn empty() -> Self {
+        Rotate(0)
+    }
+}
+
+impl Applier<Concat> for Rotate {
+    fn apply(&self, to: Concat) -> Concat {
+        let rotate = |c: char| (b'a' + (c as u8 - b'a' + self.0) % 26) as char;
+        Concat(to.0.chars().map(rotate).collect())
+    }
+}
+
+impl Reversible for Concat {
+    fn swap_sides(self) -> Self {
+        Concat(self.0.chars().rev().collect())
+    }
+}
+
+#[test]
+fn reverse() {
+    let mut rng = super::Rng(96);
+    let mut text: Vec<u8> = b"thequickbrownfoxjumpsoverthelazydog".to_vec();
+    let mut tree: SegTree<Concat, Flip<Rotate>> = text
+        .iter()
+        .map(|&c| Concat((c as char).to_string()))
+        .collect();
+    for range in random_ranges(text.len(), 200) {
+        match rng.below(3) {
+            0 => {
+                tree = tree.reverse(range.clone());
+                text[range].reverse();
+            }
+            1 => {
+                let by = rng.below(26) as u8;
+                tree = tree.apply(range.clone(), Flip::from(Rotate(by)));
+                text[range]
+                    .iter_mut()
+                    .for_each(|c| *c = b'a' + (*c - b'a' + by) % 26);
+            }
+            _ => {
+                let expected = String::from_utf8(text[range.clone()].to_vec()).unwrap();
+                assert_eq!(tree.query(range).0, expected);
+            }
+        }
+    }
+    assert_eq!(tree.query(..).0, String::from_utf8(text.clone()).unwrap());
+    assert_eq!(tree.len(), text.len());
+    assert!(depth(&tree) <= 2 * depth(&build(&[0; 35])));
+
+    let values = [2, -5, 3, -1, 4, -9, 1];
+    let tree: SegTree<_, Flip<Assign<i64>>> = values.into_iter().map(MaxSubarray::new).collect();
+    let tree = tree.reverse(1..5);
+    let expected: SegTree<_, Assign<i64>> = [2, 4, -1, 3, -5, -9, 1]
+        .into_iter()
+        .map(MaxSubarray::new)
+        .collect();
+    assert_eq!(tree.query(..), expected.query(..));
+    assert_eq!(tree.query(1..3).prefix, 4);
+    assert_eq!(tree.to_vec(), expected.to_vec());
+}
+
+#[test]
+fn reverse_traversals() {
+    // every way of reading the tree has to see the pending reversals
+    let mut rng = super::Rng(960);
+    let mut values: Vec<i64> = (0..40).map(|_| rng.below(10) as i64).collect();
+    let mut tree: SegTree<(Sum<i64>, Size), Flip<Plus<i64>>> =
+        values.iter().map(|&x| (Sum(x), Size(1))).collect();
+    for range in random_ranges(values.len(), 100) {
+        match rng.below(4) {
+            0 | 1 => {
+                tree = tree.reverse(range.clone());
+                values[range].reverse();
+            }
+            2 => {
+                let by = rng.below(5) as i64;
+                tree.apply_mut(range.clone(), Flip::from(Plus(by)));
+                values[range].iter_mut().for_each(|x| *x += by);
+            }
+            _ => {
+                let x = rng.below(10) as i64;
+                tree = tree.set(range.start, (Sum(x), Size(1)));
+                values[range.start] = x;
+            }
+        }
+
+        let sums: Vec<i64> = tree.iter().map(|(Sum(x), _)| x).collect();
+        assert_eq!(sums, values);
+        assert_eq!(
+            tree.clone().into_iter().map(|v| v.0 .0).collect::<Vec<_>>(),
+            values
+        );
+        assert_eq!(tree.normalized().to_vec(), tree.to_vec());
+        assert_eq!(tree.map::<_, NoOp, _>(|v| v).to_vec(), tree.to_vec());
+        for (i, &x) in values.iter().enumerate() {
+            assert_eq!(tree.get(i).0, Sum(x));
+        }
+        for range in random_ranges(values.len(), 5) {
+            let expected: i64 = values[range.clone()].iter().sum();
+            assert_eq!(tree.query(range.clone()).0, Sum(expected));
+            assert_eq!(
+                tree.query_prefix(range.end).0 .0,
+                values[..range.end].iter().sum::<i64>()
+            );
+            assert_eq!(
+                tree.query_suffix(range.start).0 .0,
+                values[range.start..].iter().sum::<i64>()
+            );
+            let pieces = tree.decompose(range.clone());
+            assert_eq!(pieces.into_iter().map(|v| v.0 .0).sum::<i64>(), expected);
+            let odd = values[range.clone()]
+                .iter()
+                .filter(|&&x| x % 2 == 1)
+                .count();
+            assert_eq!(tree.count_if(range.clone(), |v| v.0 .0 % 2 == 1), odd);
+            let limit = expected / 2;
+            let r = tree.max_right(range.start, |v| v.0 .0 <= limit);
+            let mut prefix = 0;
+            let expected_r = (range.start..values.len())
+                .find(|&i| {
+                    prefix += values[i];
+                    prefix > limit
+                })
+                .unwrap_or(values.len());
+            assert_eq!(r, expected_r);
+            let l = tree.min_left(range.end, |v| v.0 .0 <= limit);
+            let mut suffix = 0;
+            let expected_l = (0..range.end)
+                .rev()
+                .find(|&i| {
+                    suffix += values[i];
+                    suffix > limit
+                })
+                .map_or(0, |i| i + 1);
+            assert_eq!(l, expected_l);
+        }
+        for (range, (Sum(x), Size(n))) in tree.nodes() {
+            assert_eq!(x, values[range.clone()].iter().sum::<i64>());
+            assert_eq!(n, range.len());
+        }
+        let k = rng.below(tree.total().0 .0 as usize + 1) as i64;
+        let mut prefix = 0;
+        let expected = values.iter().position(|&x| {
+            prefix += x;
+            prefix >= k
+        });
+        assert_eq!(tree.kth(k), expected);
+    }
+}
+
+#[test]
+fn concat_order() {
+    let mut rng = super::Rng(0xC2B2AE3D27D4EB4F);
+    let letters = |s: &str| s.chars().map(|c| Concat(c.to_string())).collect::<Vec<_>>();
+    let mut text: Vec<u8> = b"thequickbrownfoxjumpsoverthelazydog".to_vec();
+    let tree = SegTree::<_, Rotate>::from(letters(core::str::from_utf8(&text).unwrap()));
+    // unbalanced shapes from concat and insert as well as the bottom-up build
+    let mut tree = SegTree::from(letters("ab"))
+        .concat(&tree)
+        .insert(5, Concat("x".into()))
+        .split(2)
+        .1
+        .remove(3);
+    for _ in 0..50 {
+        let l = rng.below(text.len() + 1);
+        let r = l + rng.below(text.len() - l + 1);
+        let k = rng.below(26) as u8;
+        tree = tree.apply(l..r, Rotate(k));
+        for c in &mut text[l..r] {
+            *c = b'a' + (*c - b'a' + k) % 26;
+        }
+        for l in 0..=text.len() {
+            for r in l..=text.len() {
+                let expected = core::str::from_utf8(&text[l..r]).unwrap();
+                assert_eq!(tree.query(l..r).0, expected);
+            }
+            assert_eq!(tree.query_prefix(l).0.as_bytes(), &text[..l]);
+            assert_eq!(tree.query_suffix(l).0.as_bytes(), &text[l..]);
+        }
+    }
+}
+
+#[test]
+fn matrix_order() {
+    // non-commutative, so any mix-up of the merge order shows up,
+    // and the entries only grow as the range does, so `pred` is monotone
+    let values: Vec<_> = (0..9i64).map(|i| Matrix([[1, i], [i % 3, 1]])).collect();
+    let tree: SegTree<_, NoOp> = values.iter().copied().collect();
+    let product = |l: usize, r: usize| {
+        values[l..r]
+            .iter()
+            .fold(Matrix::empty(), |acc, &m| Semigroup::merge(acc, m))
+    };
+    for l in 0..=values.len() {
+        for r in l..=values.len() {
+            assert_eq!(tree.query(l..r), product(l, r));
+        }
+        for limit in [1, 5, 50, 1000] {
+            let pred = |m: &Matrix<2, i64>| m.0[0][1] + m.0[0][0] <= limit;
+            let expected = (l..=values.len())
+                .take_while(|&r| pred(&product(l, r)))
+                .last()
+                .unwrap();
+            assert_eq!(tree.max_right(l, pred), expected);
+            let expected = (0..=l)
+                .rev()
+                .take_while(|&k| pred(&product(k, l)))
+                .last()
+                .unwrap();
+            assert_eq!(tree.min_left(l, pred), expected);
+        }
+    }
+}
+
+#[test]
+fn find_first_ge() {
+    let mut rng = super::Rng(0x8EBC6AF09C88C6E3);
+    let values: Vec<i64> = (0..20).map(|_| rng.below(10) as i64).collect();
+    let tree: SegTree<_, Plus<i64>> = values.iter().map(|&v| Max::Has(v - 3)).collect();
+    let tree = tree.apply(.., Plus(3));
+    for l in 0..=values.len() {
+        for r in l..=values.len() {
+            for x in -1..=10 {
+                let expected = (l..r).find(|&i| values[i] >= x);
+                assert_eq!(tree.find_first_ge(l..r, Max::Has(x)), expected);
+            }
+            assert_eq!(tree.find_first_ge(l..r, Max::NegInf), (l < r).then_some(l));
+        }
+    }
+}
+
+#[test]
+fn argmax_argmin() {
+    let mut rng = super::Rng(0xA0761D6478BD642F);
+    let mut values: Vec<i64> = (0..15).map(|_| rng.below(5) as i64).collect();
+    let mut max_tree: SegTree<_, Plus<i64>> = values.iter().copied().map(Max::Has).collect();
+    let mut min_tree: SegTree<_, Plus<i64>> = values.iter().copied().map(Min::Has).collect();
+    for _ in 0..100 {
+        let l = rng.below(values.len() + 1);
+        let r = l + rng.below(values.len() - l + 1);
+        if rng.below(3) == 0 {
+            let d = rng.below(5) as i64 - 2;
+            max_tree = max_tree.apply(l..r, Plus(d));
+            min_tree = min_tree.apply(l..r, Plus(d));
+            for v in &mut values[l..r] {
+                *v += d;
+            }
+            continue;
+        }
+        // leftmost on ties
+        let max = values[l..r].iter().max().map(|&m| {
+            (
+                l + values[l..r].iter().position(|&v| v == m).unwrap(),
+                Max::Has(m),
+            )
+        });
+        let min = values[l..r].iter().min().map(|&m| {
+            (
+                l + values[l..r].iter().position(|&v| v == m).unwrap(),
+                Min::Has(m),
+            )
+        });
+        assert_eq!(max_tree.argmax(l..r), max);
+        assert_eq!(min_tree.argmin(l..r), min);
+    }
+}
+
+#[test]
+fn max_subarray() {
+    let mut rng = super::Rng(0x5851F42D4C957F2D);
+    let mut values: Vec<i64> = (0..13).map(|_| rng.below(21) as i64 - 10).collect();
+    let mut tree: SegTree<_, Assign<i64>> = values.iter().copied().map(MaxSubarray::new).collect();
+    for _ in 0..100 {
+        let l = rng.below(values.len() + 1);
+        let r = l + rng.below(values.len() - l + 1);
+        if rng.below(2) == 0 {
+            let x = rng.below(21) as i64 - 10;
+            tree = tree.apply(l..r, Assign(Some(x)));
+            values[l..r].fill(x);
+        } else if l < r {
+            let expected = (l..r)
+                .flat_map(|i| (i + 1..=r).map(move |j| (i, j)))
+                .map(|(i, j)| values[i..j].iter().sum::<i64>())
+                .max()
+                .unwrap();
+            let result = tree.query(l..r);
+            assert_eq!(result.best, expected);
+            assert_eq!(result.sum, values[l..r].iter().sum::<i64>());
+            assert_eq!(result.len, r - l);
+        } else {
+            assert_eq!(tree.query(l..r), MaxSubarray::empty());
+        }
+    }
+}
+
+#[test]
+fn apply_mut() {
+    let mut rng = super::Rng(0x94D049BB133111EB);
+    let mut tree = build(&[3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5]);
+    let mut expected = tree.clone();
+    let mut snapshots = vec![];
+    for step in 0..200 {
+        let l = rng.below(tree.size() + 1);
+        let r = l + rng.below(tree.size() - l + 1);
+        let d = rng.below(11) as i64 - 5;
+        if step % 20 == 0 {
+            snapshots.push((tree.clone(), expected.to_vec()));
+        }
+        tree.apply_mut(l..r, Plus(d));
+        expected = expected.apply(l..r, Plus(d));
+        assert_eq!(tree, expected);
+        assert_eq!(sum(&tree, l, r), sum(&expected, l, r));
+    }
+    // the versions sharing nodes with the tree were copied on write
+    for (snapshot, values) in snapshots {
+        assert_eq!(snapshot.to_vec(), values);
+    }
+}
+
+#[test]
+fn apply_mut_in_place() {
+    let mut tree = build(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    let left_child = |tree: &Tree| match tree {
+        SegTree::Branch { left, .. } => &**left as *const Tree,
+        _ => unreachable!(),
+    };
+    let before = left_child(&tree);
+    tree.apply_mut(1..3, Plus(10));
+    assert_eq!(left_child(&tree), before);
+    assert_eq!(tree, build(&[1, 12, 13, 4, 5, 6, 7, 8]));
+}
+
+#[test]
+fn apply_batch() {
+    type Affine2 = SegTree<(Sum<i64>, Size), Affine<i64>>;
+    let mut rng = super::Rng(0xE7037ED1A0B428DB);
+    let tree: Affine2 = (0..13).map(|x| (Sum(x), Size(1))).collect();
+    let updates: Vec<_> = (0..50)
+        .map(|_| {
+            let l = rng.below(14);
+            let r = l + rng.below(14 - l);
+            let (a, b) = (rng.below(3) as i64 - 1, rng.below(5) as i64 - 2);
+            (l..r, Affine { a, b })
+        })
+        .collect();
+    let batched = tree.apply_batch(&updates);
+    let one_by_one = updates.iter().fold(tree.clone(), |tree, (range, m)| {
+        tree.apply(range.clone(), *m)
+    });
+    assert_eq!(batched, one_by_one);
+    // the original is untouched
+    assert_eq!(
+        tree.to_vec(),
+        (0..13).map(|x| (Sum(x), Size(1))).collect::<Vec<_>>()
+    );
+    assert_eq!(tree.apply_batch(&[]), tree);
+}
+
+#[test]
+fn counted() {
+    let mut rng = super::Rng(0x61C8864680B583EB);
+    let mut values: Vec<i64> = (0..12).map(|_| rng.below(10) as i64).collect();
+    let mut plus: SegTree<_, Plus<i64>> = values.iter().copied().map(Counted::new).collect();
+    let mut times: SegTree<_, Times<i64>> = values.iter().copied().map(Counted::new).collect();
+    let mut pair: SegTree<_, Times<i64>> = values.iter().map(|&v| (Sum(v), Size(1))).collect();
+    let mut expected_plus = values.clone();
+    for _ in 0..100 {
+        let l = rng.below(values.len() + 1);
+        let r = l + rng.below(values.len() - l + 1);
+        let c = rng.below(5) as i64 - 2;
+        match rng.below(3) {
+            0 => {
+                plus = plus.apply(l..r, Plus(c));
+                expected_plus[l..r].iter_mut().for_each(|v| *v += c);
+            }
+            1 => {
+                times = times.apply(l..r, Times(c));
+                pair = pair.apply(l..r, Times(c));
+                values[l..r].iter_mut().for_each(|v| *v *= c);
+            }
+            _ => {
+                let count = r - l;
+                let value = expected_plus[l..r].iter().sum();
+                assert_eq!(plus.query(l..r), Counted { value, count });
+                let value = values[l..r].iter().sum();
+                assert_eq!(times.query(l..r), Counted { value, count });
+                assert_eq!(pair.query(l..r), (Sum(value), Size(count)));
+            }
+        }
+    }
+    assert!(Times(1).is_identity() && !Times(0).is_identity());
+}
+
+#[test]
+fn assign_sum() {
+    let mut rng = super::Rng(0x2545F4914F6CDD1D);
+    let mut values: Vec<i64> = (0..20).map(|_| rng.below(10) as i64).collect();
+    let mut tree: SegTree<_, Assign<i64>> = values.iter().map(|&v| (Sum(v), Size(1))).collect();
+    for _ in 0..300 {
+        let l = rng.below(values.len() + 1);
+        let r = l + rng.below(values.len() - l + 1);
+        if rng.below(2) == 0 {
+            // overlapping assignments, the later one has to win
+            let x = rng.below(21) as i64 - 10;
+            tree = tree.apply(l..r, Assign(Some(x)));
+            values[l..r].fill(x);
+        } else {
+            assert_eq!(tree.query(l..r).0 .0, values[l..r].iter().sum::<i64>());
+        }
+    }
+    assert_eq!(tree.apply(.., Assign(None)), tree);
+}
+
+#[test]
+fn rolling_hash() {
+    type Hash = RollingHash<911_382_323, 972_663_749>;
+    let direct = |s: &[u8]| {
+        s.iter().fold(0u64, |h, &c| {
+            ((h as u128 * 911_382_323 + c as u128) % 972_663_749) as u64
+        })
+    };
+    let mut text = b"mississippi".to_vec();
+    let mut tree: SegTree<_, Assign<u64>> = text.iter().map(|&c| Hash::new(c as u64)).collect();
+    for (range, c) in [(1..4, b'a'), (0..11, b'z'), (5..6, b'q'), (2..9, b'b')] {
+        for l in 0..=text.len() {
+            for r in l..=text.len() {
+                let hash = tree.query(l..r);
+                assert_eq!(hash.hash, direct(&text[l..r]));
+                assert_eq!(hash.len, r - l);
+            }
+        }
+        tree = tree.apply(range.clone(), Assign(Some(c as u64)));
+        text[range].fill(c);
+    }
+    assert_eq!(
+        Hash::new(7).repeat(5),
+        (0..5)
+            .map(|_| Hash::new(7))
+            .fold(Hash::empty(), Semigroup::merge)
+    );
+}
+
+#[test]
+fn min_count() {
+    let mut rng = super::Rng(0x853C49E6748FEA9B);
+    let mut values: Vec<i32> = (0..12).map(|_| rng.below(4) as i32).collect();
+    let mut tree: SegTree<_, Plus<i32>> = values.iter().copied().map(MinCount::new).collect();
+    for _ in 0..200 {
+        let l = rng.below(values.len() + 1);
+        let r = l + rng.below(values.len() - l + 1);
+        if rng.below(2) == 0 {
+            let d = rng.below(5) as i32 - 2;
+            tree = tree.apply(l..r, Plus(d));
+            for v in &mut values[l..r] {
+                *v += d;
+            }
+        } else {
+            let expected = match values[l..r].iter().min() {
+                Some(&min) => MinCount {
+                    min,
+                    count: values[l..r].iter().filter(|&&v| v == min).count(),
+                },
+                None => MinCount::empty(),
+            };
+            assert_eq!(tree.query(l..r), expected);
+        }
+    }
+    // applying on an empty range keeps the sentinel as it is
+    let empty = SegTree::<MinCount<u8>, Plus<u8>>::Empty;
+    assert_eq!(empty.apply(.., Plus(1)).query(..), MinCount::empty());
+    assert_eq!(Plus(1u8).apply(MinCount::<u8>::empty()), MinCount::empty());
+}
+
+/// The recursive query `SegTree::query` used to be, as a reference
+fn recursive_query<V: Monoid + Clone, M: Monoid + Applier<V>>(
+    tree: &SegTree<V, M>,
+    range: core::ops::Range<usize>,
+) -> V {
+    match tree {
+        SegTree::Empty => V::empty(),
+        SegTree::Unit(v) if range.contains(&0) => v.clone(),
+        SegTree::Unit(_) => V::empty(),
+        SegTree::Branch { size, value, .. } if range.start == 0 && *size <= range.end => {
+            value.clone()
+        }
+        SegTree::Branch {
+            modifier,
+            left,
+            right,
+            ..
+        } => {
+            let mid = left.size();
+            modifier.apply(if range.end <= mid {
+                recursive_query(left, range)
+            } else if mid <= range.start {
+                recursive_query(right, range.start - mid..range.end - mid)
+            } else {
+                V::merge(
+                    recursive_query(left, range.start..mid),
+                    recursive_query(right, 0..range.end - mid),
+                )
+            })
+        }
+    }
+}
+
+#[test]
+fn iterative_query() {
+    let mut rng = super::Rng(0xDA942042E4DD58B5);
+    for len in [0, 1, 2, 3, 7, 16, 33] {
+        let mut values: Vec<i64> = (0..len).map(|_| rng.below(100) as i64).collect();
+        let mut tree: SegTree<_, Affine<i64>> = values.iter().map(|&v| (Sum(v), Size(1))).collect();
+        for _ in 0..200 {
+            let l = rng.below(len + 1);
+            let r = l + rng.below(len - l + 1);
+            if rng.below(2) == 0 {
+                let (a, b) = (rng.below(3) as i64, rng.below(5) as i64 - 2);
+                tree = tree.apply(l..r, Affine { a, b });
+                for v in &mut values[l..r] {
+                    *v = a * *v + b;
+                }
+            } else {
+                let result = tree.query(l..r);
+                assert_eq!(result, recursive_query(&tree, l..r));
+                assert_eq!(result.0 .0, values[l..r].iter().sum::<i64>());
+                assert_eq!(result.1 .0, r - l);
+            }
+        }
+    }
+}
+
+#[test]
+fn stacked_modifiers() {
+    // bursts of nested, overlapping updates stack up several pending
+    // modifiers on the same nodes before any query pushes them down
+    let mut rng = super::Rng(0x165667B19E3779F9);
+    let len = 40;
+    let mut values: Vec<i64> = (0..len).map(|_| rng.below(10) as i64).collect();
+    let mut tree: SegTree<_, Affine<i64>> = values.iter().map(|&v| (Sum(v), Size(1))).collect();
+    for _ in 0..30 {
+        let (mut l, mut r) = (0, len);
+        for _ in 0..rng.below(8) + 1 {
+            // a = -1 does not commute with adding b
+            let (a, b) = (rng.below(3) as i64 - 1, rng.below(7) as i64 - 3);
+            tree = tree.apply(l..r, Affine { a, b });
+            for v in &mut values[l..r] {
+                *v = a * *v + b;
+            }
+            // mostly shrinking ranges, sometimes sticking out
+            l = (l + rng.below(5)).min(len);
+            r = (r - rng.below(5).min(r)).max(l);
+            if rng.below(4) == 0 {
+                (l, r) = (l.saturating_sub(7), (r + 7).min(len));
+            }
+        }
+        for l in 0..=len {
+            for r in l..=len {
+                assert_eq!(tree.query(l..r).0 .0, values[l..r].iter().sum::<i64>());
+            }
+        }
+        assert_eq!(tree.iter().map(|(v, _)| v.0).collect::<Vec<_>>(), values);
+    }
+}
+
+#[test]
+fn inclusive() {
+    let tree = build(&[3, 1, 4, 1, 5]);
+    assert_eq!(tree.query_inclusive(1, 3).0 .0, 6);
+    assert_eq!(tree.query_inclusive(0, 4).0 .0, 14);
+    assert_eq!(tree.query_inclusive(2, 2).0 .0, 4);
+    let tree = tree.apply_inclusive(3, 4, Plus(10));
+    assert_eq!(tree, build(&[3, 1, 4, 11, 15]));
+}
+
+#[test]
+#[should_panic(expected = "range end 6 out of bounds for tree of size 5")]
+fn inclusive_out_of_bounds() {
+    build(&[3, 1, 4, 1, 5]).query_inclusive(0, 5);
+}
+
+#[test]
+#[should_panic(expected = "range end overflow")]
+fn inclusive_overflow() {
+    build(&[3, 1, 4, 1, 5]).apply_inclusive(0, usize::MAX, Plus(1));
+}
+
+#[test]
+fn prefix_scan() {
+    let tree = build(&[3, 1, 4, 1, 5, 9, 2]).apply(2..5, Plus(10));
+    let expected: Vec<_> = (1..=tree.size()).map(|n| tree.query(..n)).collect();
+    assert_eq!(tree.prefix_scan(), expected);
+    assert_eq!(tree.prefix_scan().last().unwrap().0 .0, 55);
+    assert!(Tree::Empty.prefix_scan().is_empty());
+}
+
+#[test]
+fn prefix_suffix() {
+    for len in [0usize, 1, 2, 5, 8, 13] {
+        let values: Vec<i64> = (1..=len as i64).collect();
+        let tree = build(&values).apply(len / 3..len.div_ceil(2), Plus(7));
+        for n in 0..=len {
+            assert_eq!(tree.query_prefix(n), tree.query(..n));
+            assert_eq!(tree.query_suffix(n), tree.query(n..));
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "out of bounds")]
+fn prefix_out_of_bounds() {
+    build(&[1, 2, 3]).query_prefix(4);
+}
+
+#[test]
+fn kth() {
+    let counts = [0i64, 2, 0, 3, 1, 0, 4];
+    let tree = build(&counts);
+    for k in 1..=10 {
+        let expected = (0..counts.len()).find(|&i| counts[..=i].iter().sum::<i64>() >= k);
+        assert_eq!(tree.kth(k), expected);
+    }
+    assert_eq!(tree.kth(11), None);
+    assert_eq!(tree.kth(0), Some(0));
+
+    // pending modifiers are taken into account
+    let tree = tree.apply(0..3, Plus(1));
+    assert_eq!(tree.kth(1), Some(0));
+    assert_eq!(tree.kth(4), Some(1));
+    assert_eq!(tree.kth(5), Some(2));
+    assert_eq!(tree.kth(6), Some(3));
+
+    let sizes: SegTree<Size, NoOp> = (0..5).map(|_| Size(1)).collect();
+    assert_eq!(sizes.kth(3), Some(2));
+    assert_eq!(Tree::Empty.kth(0), None);
+}
+
+#[test]
+fn kth_in_difference() {
+    // the k-th smallest of a[l..r], using one count tree per prefix
+    let mut rng = super::Rng(17);
+    let a: Vec<usize> = (0..40).map(|_| rng.below(10)).collect();
+    let mut prefixes = vec![build(&[0; 10])];
+    for &x in &a {
+        let last = prefixes.last().unwrap();
+        prefixes.push(last.apply(x..x + 1, Plus(1)));
+    }
+    for core::ops::Range { start: l, end: r } in random_ranges(a.len(), 50) {
+        let mut sorted = a[l..r].to_vec();
+        sorted.sort();
+        for k in 1..=sorted.len() {
+            let kth = Tree::kth_in_difference(&prefixes[l], &prefixes[r], k as i64);
+            assert_eq!(kth, Some(sorted[k - 1]), "{}..{} k={}", l, r, k);
+        }
+        let kth = Tree::kth_in_difference(&prefixes[l], &prefixes[r], sorted.len() as i64 + 1);
+        assert_eq!(kth, None);
+    }
+    assert_eq!(Tree::kth_in_difference(&Tree::Empty, &Tree::Empty, 1), None);
+}
+
+#[test]
+fn apply_tracked() {
+    let tree = build(&[1, 2, 3, 4, 5]);
+    let (applied, touched) = tree.apply_tracked(1..=3, Plus(10));
+    assert_eq!(applied, tree.apply(1..4, Plus(10)));
+    assert_eq!(touched, 1..4);
+    assert_eq!(tree.apply_tracked(2.., Plus(1)).1, 2..5);
+    assert_eq!(tree.apply_tracked(.., Plus(1)).1, 0..5);
+
+    // nothing changed
+    let (same, touched) = tree.apply_tracked(1..4, Plus(0));
+    assert_eq!(same, tree);
+    assert!(touched.is_empty());
+    assert_eq!(tree.apply_tracked(3..3, Plus(1)).1, 3..3);
+}
+
+#[test]
+fn nodes() {
+    let tree = build(&[1, 2, 3, 4, 5])
+        .apply(0..4, Plus(10))
+        .apply(1..3, Plus(1));
+    let nodes: Vec<_> = tree.nodes().collect();
+    assert_eq!(nodes[0].0, 0..5);
+    for (range, value) in &nodes {
+        assert_eq!(*value, tree.query(range.clone()), "{:?}", range);
+    }
+    // a parent comes before its children
+    for (i, (range, _)) in nodes.iter().enumerate() {
+        for (child, _) in &nodes[..i] {
+            assert!(!(range.start <= child.start && child.end <= range.end && child != range));
+        }
+    }
+    let leaves: Vec<_> = nodes.iter().filter(|(range, _)| range.len() == 1).collect();
+    assert_eq!(leaves.len(), 5);
+    assert!(leaves
+        .iter()
+        .enumerate()
+        .all(|(i, (range, _))| range.start == i));
+    assert_eq!(nodes.len(), 9);
+
+    assert_eq!(Tree::Empty.nodes().count(), 0);
+}
+
+#[test]
+fn float_max_min() {
+    let values = [2.5, -1.0, f64::NAN, 7.0, f64::NEG_INFINITY];
+    let max: SegTree<Max<TotalOrd<f64>>, Plus<f64>> =
+        values.iter().map(|&x| Max::Has(TotalOrd(x))).collect();
+    let min: SegTree<Min<TotalOrd<f64>>, Plus<f64>> =
+        values.iter().map(|&x| Min::Has(TotalOrd(x))).collect();
+
+    assert_eq!(max.query(..2), Max::Has(TotalOrd(2.5)));
+    assert_eq!(max.query(3..), Max::Has(TotalOrd(7.0)));
+    assert_eq!(max.query(2..2), Max::NegInf);
+    assert_eq!(min.query(..), Min::Has(TotalOrd(f64::NEG_INFINITY)));
+    assert_eq!(min.apply(.., Plus(1.0)).query(..2), Min::Has(TotalOrd(0.0)));
+
+    // NaN is the largest value: it wins a maximum and never a minimum
+    match max.query(..) {
+        Max::Has(TotalOrd(x)) => assert!(x.is_nan()),
+        Max::NegInf => unreachable!(),
+    }
+    assert_eq!(min.query(1..4), Min::Has(TotalOrd(-1.0)));
+}
+
+#[test]
+fn fill() {
+    let mut rng = super::Rng(95);
+    let mut values: Vec<i64> = (0..37).map(|_| rng.below(100) as i64).collect();
+    let mut tree = build(&values);
+    for range in random_ranges(values.len(), 100) {
+        let x = rng.below(100) as i64 - 50;
+        if rng.below(2) == 0 {
+            tree = tree.fill(range.clone(), (Sum(x), Size(1)));
+            values[range].fill(x);
+        } else {
+            tree = tree.apply(range.clone(), Plus(x));
+            values[range].iter_mut().for_each(|v| *v += x);
+        }
+        assert_eq!(tree, build(&values));
+        assert_eq!(
+            tree.query(..),
+            (Sum(values.iter().sum()), Size(values.len()))
+        );
+    }
+    assert!(depth(&tree) <= depth(&build(&values)));
+
+    // the filled halves share their nodes
+    let filled = build(&[0; 1024]).fill(.., (Sum(1), Size(1)));
+    assert_eq!(filled.query(..).0, Sum(1024));
+    assert_eq!(Tree::shared_node_count(&[&filled]), 11);
+
+    // the shape of `build` is kept, so fill-derived versions walk in lockstep
+    for len in 1..20 {
+        let tree = build(&vec![0; len]);
+        let (older, newer) = (
+            tree.fill(..len / 2, (Sum(1), Size(1))),
+            tree.fill(.., (Sum(2), Size(1))),
+        );
+        let shape = |tree: &Tree| tree.nodes().map(|(range, _)| range).collect::<Vec<_>>();
+        assert_eq!(shape(&older), shape(&tree));
+        assert_eq!(shape(&newer), shape(&tree));
+        assert_eq!(Tree::kth_in_difference(&older, &newer, 1), Some(0));
+        let k = (2 * len - len / 2) as i64;
+        assert_eq!(Tree::kth_in_difference(&older, &newer, k), Some(len - 1));
+        assert_eq!(Tree::kth_in_difference(&older, &newer, k + 1), None);
+    }
+}
+
+#[test]
+fn checked() {
+    let tree = build(&[1, 2, 3, 4, 5]);
+    assert_eq!(tree.query_checked(1..4), Some(tree.query(1..4)));
+    assert_eq!(tree.query_checked(..), Some(tree.query(..)));
+    assert_eq!(tree.query_checked(5..5), Some((Sum(0), Size(0))));
+    assert_eq!(tree.query_checked(0..6), None);
+    assert_eq!(tree.query_checked(..=5), None);
+    assert_eq!(tree.query_checked(..=usize::MAX), None);
+    let (l, r) = (3, 2);
+    assert_eq!(tree.query_checked(l..r), None);
+
+    assert_eq!(
+        tree.apply_checked(1..4, Plus(1)),
+        Some(tree.apply(1..4, Plus(1)))
+    );
+    assert_eq!(
+        tree.apply_checked(4.., Plus(1)),
+        Some(tree.apply(4.., Plus(1)))
+    );
+    assert_eq!(tree.apply_checked(2..7, Plus(1)), None);
+    assert_eq!(tree.apply_checked(l..r, Plus(0)), None);
+    assert_eq!(Tree::Empty.query_checked(..), Some((Sum(0), Size(0))));
+}
+
+#[test]
+fn truncate() {
+    let values = [3, 1, 4, 1, 5, 9, 2];
+    let tree = build(&values).apply(2..6, Plus(10));
+    for len in 0..values.len() {
+        let truncated = tree.truncate(len);
+        assert_eq!(truncated.to_vec(), tree.to_vec()[..len]);
+        assert!(depth(&truncated) <= depth(&tree));
+    }
+    assert_eq!(tree.truncate(0), Tree::Empty);
+    assert_eq!(tree.truncate(values.len()), tree);
+    assert_eq!(tree.truncate(100), tree);
+    assert_eq!(Tree::Empty.truncate(3), Tree::Empty);
+}
+
+#[test]
+fn map_range() {
+    let mut rng = super::Rng(100);
+    let mut values: Vec<i64> = (0..37).map(|_| rng.below(100) as i64).collect();
+    let mut tree = build(&values);
+    for range in random_ranges(values.len(), 100) {
+        if rng.below(2) == 0 {
+            // not expressible as a modifier
+            tree = tree.map_range(range.clone(), |i, (Sum(v), n)| {
+                (Sum(v * v % 97 + i as i64), n)
+            });
+            for i in range {
+                values[i] = values[i] * values[i] % 97 + i as i64;
+            }
+        } else {
+            let x = rng.below(100) as i64 - 50;
+            tree = tree.apply(range.clone(), Plus(x));
+            values[range].iter_mut().for_each(|v| *v += x);
+        }
+        assert_eq!(tree, build(&values));
+        assert_eq!(
+            tree.query(..),
+            (Sum(values.iter().sum()), Size(values.len()))
+        );
+    }
+
+    // only the leaves in the range are visited
+    let visited = core::cell::RefCell::new(vec![]);
+    let mapped = tree.map_range(3..7, |i, v| {
+        visited.borrow_mut().push(i);
+        v
+    });
+    assert_eq!(visited.into_inner(), vec![3, 4, 5, 6]);
+    assert_eq!(mapped, tree);
+    assert_eq!(tree.map_range(4..4, |_, _| unreachable!()), tree);
+}
+
+#[test]
+fn try_build() {
+    let input = ["3", "1", "x", "4", "y"];
+    let calls = core::cell::Cell::new(0);
+    let parse = |i: usize| {
+        calls.set(calls.get() + 1);
+        input[i].parse().map(|v| (Sum(v), Size(1)))
+    };
+    assert_eq!(Tree::try_build(2, parse), Ok(build(&[3, 1])));
+    calls.set(0);
+    assert!(Tree::try_build(5, parse).is_err());
+    // stopped at the first error
+    assert_eq!(calls.get(), 3);
+    assert_eq!(Tree::try_build(0, parse), Ok(Tree::Empty));
+}
+
+#[test]
+fn zeros() {
+    let tree = SegTree::<Max<i64>, Plus<i64>>::zeros(5);
+    assert_eq!(tree.size(), 5);
+    assert_eq!(tree.query(..), Max::NegInf);
+    let tree = tree.set(3, Max::Has(7)).set(1, Max::Has(2));
+    assert_eq!(
+        tree.to_vec(),
+        [
+            Max::NegInf,
+            Max::Has(2),
+            Max::NegInf,
+            Max::Has(7),
+            Max::NegInf
+        ]
+    );
+    assert_eq!(Tree::zeros(0), Tree::Empty);
+    assert!(matches!(Tree::zeros(0), SegTree::Empty));
+}
+
+#[test]
+fn build_from_iter() {
+    let mut read = Vec::new();
+    let mut input = (1..=10).inspect(|&x| read.push(x));
+    let tree = Tree::build_from_iter(6, (&mut input).map(|x| (Sum(x), Size(1))));
+    assert_eq!(tree, build(&[1, 2, 3, 4, 5, 6]));
+    assert_eq!(input.next(), Some(7));
+    assert_eq!(read, (1..=7).collect::<Vec<_>>());
+    assert_eq!(Tree::build_from_iter(0, core::iter::empty()), Tree::Empty);
+}
+
+#[test]
+#[should_panic(expected = "iterator ended after 3 of 4 items")]
+fn build_from_short_iter() {
+    Tree::build_from_iter(4, (0..3).map(|x| (Sum(x), Size(1))));
+}
+
+#[test]
+fn empty_ranges() {
+    for len in 0..=17 {
+        let values: Vec<i64> = (1..=len as i64).collect();
+        let tree = build(&values).apply(len / 4..len / 2, Plus(3));
+        for i in 0..=len {
+            assert_eq!(tree.query(i..i), <(Sum<i64>, Size)>::empty());
+            let same = tree.apply(i..i, Plus(100));
+            assert_eq!(same, tree);
+            // only the root is copied, every other node is shared
+            assert_eq!(
+                SegTree::shared_node_count(&[&tree, &same]),
+                tree.node_count() + (len > 0) as usize
+            );
+        }
+    }
+}
+
+#[test]
+fn count_if() {
+    let values = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3];
+    let tree = build(&values).apply(2..7, Plus(1));
+    let updated: Vec<i64> = tree.iter().map(|(v, _)| v.0).collect();
+    let even = |v: &(Sum<i64>, Size)| v.0 .0 % 2 == 0;
+    for l in 0..=values.len() {
+        for r in l..=values.len() {
+            let expected = updated[l..r].iter().filter(|&&v| v % 2 == 0).count();
+            assert_eq!(tree.count_if(l..r, even), expected);
+        }
+    }
+    assert_eq!(tree.count_if(.., |_| true), values.len());
+    assert_eq!(Tree::Empty.count_if(.., |_| true), 0);
+}
+
+#[test]
+fn rebuild() {
+    let mut tree = Tree::Empty;
+    for i in 0..100 {
+        tree = tree.insert(i / 2, (Sum(i as i64), Size(1)));
+    }
+    let tree = tree.apply(10..90, Plus(1));
+    let rebuilt = tree.rebuild();
+    assert_eq!(rebuilt, tree);
+    assert_eq!(depth(&rebuilt), 7);
+    assert_eq!(
+        Tree::shared_node_count(&[&tree, &rebuilt]),
+        tree.node_count() + rebuilt.node_count()
+    );
+}
+
+#[test]
+fn normalized() {
+    let tree = build(&[1, 2, 3, 4, 5, 6, 7])
+        .apply(1..6, Plus(10))
+        .apply(0..4, Plus(100))
+        .apply(.., Plus(1000));
+    let normalized = tree.normalized();
+    assert_eq!(normalized, tree);
+    assert_eq!(depth(&normalized), depth(&tree));
+
+    let mut stack = vec![&normalized];
+    while let Some(node) = stack.pop() {
+        if let SegTree::Branch {
+            modifier,
+            value,
+            left,
+            right,
+            ..
+        } = node
+        {
+            assert_eq!(*modifier, Plus(0));
+            assert_eq!(*value, left.query(..).merge(right.query(..)));
+            stack.push(left);
+            stack.push(right);
+        }
+    }
+}
+
+#[test]
+fn checked_sum() {
+    let tree: SegTree<_, Plus<i32>> = [1, 2, 3]
+        .into_iter()
+        .map(|x| (CheckedSum(x), Size(1)))
+        .collect();
+    let tree = tree.apply(1.., Plus(10));
+    assert_eq!(tree.query(..).0, CheckedSum(26));
+    assert_eq!(tree.query(2..).0, CheckedSum(13));
+}
+
+#[test]
+#[should_panic(expected = "CheckedSum overflowed: 2147483647 + 1")]
+fn checked_sum_overflow() {
+    // the build already merges the two
+    let _: SegTree<_, NoOp> = [i32::MAX, 1].into_iter().map(CheckedSum).collect();
+}
+
+#[test]
+#[should_panic(expected = "CheckedSum overflowed")]
+fn checked_sum_apply_overflow() {
+    let tree: SegTree<_, Plus<i8>> = (0..10).map(|_| (CheckedSum(0i8), Size(1))).collect();
+    tree.apply(.., Plus(13));
+}
+
+#[test]
+fn tuples() {
+    let values = [3i64, -1, 4, 1, -5, 9, 2];
+    type Mods = (Plus<i64>, Plus<i64>, Plus<i64>, NoOp);
+    let tree: SegTree<_, Mods> = values
+        .iter()
+        .map(|&v| {
+            (
+                (Sum(v), Size(1)),
+                Min::Has(v),
+                Max::Has(v),
+                Gcd(v.unsigned_abs()),
+            )
+        })
+        .collect();
+    let tree = tree.apply(2..5, (Plus(1), Plus(1), Plus(1), NoOp));
+    let ((total, n), min, max, gcd) = tree.query(1..6);
+    assert_eq!((total, n), (Sum(-1 + 5 + 2 - 4 + 9), Size(5)));
+    assert_eq!((min, max, gcd), (Min::Has(-4), Max::Has(9), Gcd(1)));
+
+    type Five = (Sum<i32>, Xor<u8>, BitOr<u8>, Size, Single<char>);
+    let five: Five = Monoid::empty();
+    assert!(five.is_identity());
+    let merged = Semigroup::merge(five, (Sum(1), Xor(3), BitOr(4), Size(1), Single(Some('a'))));
+    assert_eq!(
+        merged,
+        (Sum(1), Xor(3), BitOr(4), Size(1), Single(Some('a')))
+    );
+}
+
+#[test]
+fn option_values() {
+    let tree: SegTree<Option<(Sum<i64>, Size)>, Affine<i64>> =
+        (1..=5).map(|v| Some((Sum(v), Size(1)))).collect();
+    let tree = tree.apply(1..3, Affine { a: 2, b: 1 });
+    assert_eq!(tree.query(..), Some((Sum(1 + 5 + 7 + 4 + 5), Size(5))));
+    assert_eq!(tree.query(2..2), None);
+    assert_eq!(
+        tree.apply(2..2, Affine { a: 0, b: 3 }).query(..2),
+        Some((Sum(6), Size(2)))
+    );
+    assert_eq!(Plus(3).apply(None::<(Sum<i64>, Size)>), None);
+}
+
+#[test]
+fn versions() {
+    let mut versions = vec![build(&[1, 2, 3, 4])];
+    for i in 0..4 {
+        let next = versions[i].apply(i..4, Plus(1));
+        versions.push(next);
+    }
+    let copies = versions.clone();
+    for (i, tree) in copies.iter().enumerate() {
+        assert_eq!(
+            sum(tree, 0, 4),
+            10 + (0..i as i64).map(|j| 4 - j).sum::<i64>()
+        );
+    }
+}
+
+#[test]
+fn history() {
+    let base = build(&[1, 2, 3, 4]);
+    let versions = Tree::history(
+        base.clone(),
+        [(0..2, Plus(10)), (1..4, Plus(100)), (3..4, Plus(-4))],
+    );
+    assert_eq!(versions.len(), 4);
+    assert_eq!(versions[0], base);
+    assert_eq!(versions[1], build(&[11, 12, 3, 4]));
+    assert_eq!(versions[2], build(&[11, 112, 103, 104]));
+    assert_eq!(versions[3], build(&[11, 112, 103, 100]));
+    assert_eq!(Tree::history(base.clone(), []), vec![base]);
+}
+
+#[test]
+fn versioned() {
+    let mut history = Versioned::new(build(&[1, 2, 3, 4]));
+    assert_eq!(history.apply(0..2, Plus(10)), 1);
+    assert_eq!(history.apply(1..4, Plus(100)), 2);
+    assert_eq!(history.query_at(0, ..).0 .0, 10);
+    assert_eq!(history.query_at(1, ..).0 .0, 30);
+    assert_eq!(history.query_at(2, ..).0 .0, 330);
+
+    // applying to an old version forks the history
+    history.rollback(1);
+    assert_eq!(history.current(), 1);
+    assert_eq!(history.apply(3..4, Plus(-4)), 3);
+    assert_eq!(history.at(3), &build(&[11, 12, 3, 0]));
+    assert_eq!(history.at(2), &build(&[11, 112, 103, 104]));
+    assert_eq!(history.len(), 4);
+
+    history.rollback(0);
+    assert_eq!(history.apply(.., Plus(1)), 4);
+    assert_eq!(history.query_at(4, 1..3).0 .0, 7);
+}
+
+#[test]
+#[should_panic(expected = "version 2 does not exist, there are 2 versions")]
+fn versioned_rollback_out_of_bounds() {
+    let mut history = Versioned::new(build(&[1, 2, 3, 4]));
+    history.apply(.., Plus(1));
+    history.rollback(2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn cached() {
+    let mut cached = CachedSegTree::new(build(&[1, 2, 3, 4]));
+    assert_eq!(cached.query(1..3).0 .0, 5);
+    // the same range written differently hits the same entry
+    assert_eq!(cached.query(1..=2).0 .0, 5);
+    assert_eq!(cached.query(..).0 .0, 10);
+
+    cached.apply(2..4, Plus(10));
+    assert_eq!(cached.query(1..3).0 .0, 15);
+    assert_eq!(cached.query(..).0 .0, 30);
+    assert_eq!(cached.tree(), &build(&[1, 2, 13, 14]));
+    assert_eq!(cached.into_inner().query(0..0).0 .0, 0);
+}
+
+#[test]
+fn one_indexed() {
+    let values = [3, 1, 4, 1, 5];
+    let tree = OneIndexed::new(build(&values));
+    for l in 1..=values.len() {
+        for r in l - 1..=values.len() {
+            let expected = values[l - 1..r].iter().sum::<i64>();
+            assert_eq!(tree.query(l..=r).0 .0, expected, "{}..={}", l, r);
+        }
+    }
+    let applied = tree.apply(2..=4, Plus(10));
+    assert_eq!(applied.tree(), &build(&values).apply(1..4, Plus(10)));
+    // l..=l - 1 is empty
+    let l = 3;
+    assert_eq!(
+        applied.apply(l..=l - 1, Plus(1)).into_inner(),
+        applied.into_inner()
+    );
+}
+
+#[test]
+#[should_panic(expected = "range 0..=2 starts before index 1")]
+fn one_indexed_zero() {
+    OneIndexed::new(build(&[1, 2, 3])).query(0..=2);
+}
+
+#[test]
+#[should_panic(expected = "range end 4 out of bounds for tree of size 3")]
+fn one_indexed_out_of_bounds() {
+    OneIndexed::new(build(&[1, 2, 3])).query(2..=4);
+}
+
+#[test]
+fn into_iter() {
+    let tree = build(&[3, 1, 4, 1, 5, 9, 2]).apply(2..6, Plus(10));
+    let expected = tree.to_vec();
+    let snapshot = tree.apply(0..1, Plus(1));
+    assert_eq!(tree.into_iter().collect::<Vec<_>>(), expected);
+    // consuming a version leaves the ones sharing its nodes intact
+    assert_eq!(snapshot.query(..).0 .0, 66);
+    let mut sum = 0;
+    for (v, _) in snapshot {
+        sum += v.0;
+    }
+    assert_eq!(sum, 66);
+}
+
+#[test]
+fn into_iter_moves_unshared_values() {
+    std::thread_local!(static CLONES: core::cell::Cell<usize> = const { core::cell::Cell::new(0) });
+
+    #[derive(Debug, PartialEq)]
+    struct Tracked(i32);
+    impl Clone for Tracked {
+        fn clone(&self) -> Self {
+            CLONES.with(|c| c.set(c.get() + 1));
+            Tracked(self.0)
+        }
+    }
+    impl Semigroup for Tracked {
+        fn merge(self, other: Self) -> Self {
+            Tracked(self.0 + other.0)
+        }
+    }
+    impl Monoid for Tracked {
+        fn empty() -> Self {
+            Tracked(0)
+        }
+    }
+
+    let tree: SegTree<_, NoOp> = (0..10).map(Tracked).collect();
+    let copy = tree.clone();
+    CLONES.with(|c| c.set(0));
+    let values: Vec<_> = copy.into_iter().collect();
+    assert_eq!(values, (0..10).map(Tracked).collect::<Vec<_>>());
+    // the copy shares every node below the root with `tree`,
+    // so the 10 leaves and 8 branches under the root are cloned
+    assert_eq!(CLONES.with(|c| c.get()), 10 + 8);
+
+    CLONES.with(|c| c.set(0));
+    assert_eq!(tree.into_iter().count(), 10);
+    assert_eq!(CLONES.with(|c| c.get()), 0);
+}
+
+#[test]
+fn hash() {
+    use std::collections::HashSet;
+    use std::hash::{BuildHasher, RandomState};
+
+    let a = build(&[1, 2, 3, 4, 5]).apply(1..4, Plus(10));
+    let b = build(&[1, 2, 13, 4, 5])
+        .apply(1..2, Plus(10))
+        .apply(3..4, Plus(10));
+    let c = build(&[11, 12, 13, 14]).concat(&build(&[5]));
+    let state = RandomState::new();
+    assert_eq!(state.hash_one(&a), state.hash_one(&b));
+
+    let set: HashSet<_> = [a, b, c, build(&[1, 12, 13, 14, 5])].into_iter().collect();
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(
+        &build(&[11, 12, 13, 14, 15])
+            .apply(.., Plus(-10))
+            .apply(1..4, Plus(10))
+    ));
+}
+
+#[test]
+fn arc_across_threads() {
+    let tree: ArcSegTree<_, Plus<i64>> = (0..1000).map(|i| (Sum(i), Size(1))).collect();
+    let tree = tree.apply(100..200, Plus(1));
+    let handles: Vec<_> = (0..4)
+        .map(|k| {
+            let tree = tree.clone();
+            std::thread::spawn(move || tree.apply(k * 250..(k + 1) * 250, Plus(2)).query(..).0)
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), Sum(499_500 + 100 + 500));
+    }
+    assert_eq!(tree.query(150..250).0, Sum((150..250).sum::<i64>() + 50));
+}
+
+#[test]
+fn uneven_shapes() {
+    // the bottom-up build leaves the last node unpaired on odd levels
+    for len in 1..40 {
+        let values: Vec<i64> = (0..len).map(|i| i * i % 17).collect();
+        let tree = build(&values).apply(len as usize / 3.., Plus(2));
+        let expected: Vec<i64> = (0..len as usize)
+            .map(|i| values[i] + if i >= len as usize / 3 { 2 } else { 0 })
+            .collect();
+        for l in 0..expected.len() {
+            for r in l..=expected.len() {
+                assert_eq!(sum(&tree, l, r), expected[l..r].iter().sum::<i64>());
+            }
+            assert_eq!(tree.get(l).0 .0, expected[l]);
+        }
+    }
+}
+
+#[bench]
+fn build_1e6(b: &mut test::Bencher) {
+    b.iter(|| Tree::build(1_000_000, |i| (Sum(i as i64), Size(1))));
+}
+
+/// The recursive top-down build that `SegTree::build` replaced, kept only
+/// as the baseline of `build_1e6`
+fn recursive_build<F: Fn(usize) -> (Sum<i64>, Size) + Copy>(
+    offset: usize,
+    len: usize,
+    init: F,
+) -> Tree {
+    match len {
+        0 => SegTree::Empty,
+        1 => SegTree::Unit(init(offset)),
+        len => {
+            let mid = len / 2;
+            let (l, r) = (
+                recursive_build(offset, mid, init),
+                recursive_build(offset + mid, len - mid, init),
+            );
+            SegTree::Branch {
+                size: len,
+                modifier: Plus(0),
+                value: Semigroup::merge(l.total(), r.total()),
+                left: std::rc::Rc::new(l),
+                right: std::rc::Rc::new(r),
+            }
+        }
+    }
+}
+
+#[test]
+fn recursive_build_matches() {
+    let values: Vec<i64> = (0..37).collect();
+    let tree = recursive_build(0, values.len(), |i| (Sum(i as i64), Size(1)));
+    assert_eq!(tree, build(&values));
+}
+
+#[bench]
+fn recursive_build_1e6(b: &mut test::Bencher) {
+    b.iter(|| recursive_build(0, 1_000_000, |i| (Sum(i as i64), Size(1))));
+}
+
+fn random_ranges(len: usize, count: usize) -> Vec<core::ops::Range<usize>> {
+    let mut rng = super::Rng(0x9E3779B97F4A7C15);
+    (0..count)
+        .map(|_| {
+            let (a, b) = (rng.below(len + 1), rng.below(len + 1));
+            a.min(b)..a.max(b)
+        })
+        .collect()
+}
+
+#[bench]
+fn query_1e6(b: &mut test::Bencher) {
+    let tree = Tree::build(1_000_000, |i| (Sum(i as i64), Size(1)));
+    let ranges = random_ranges(1_000_000, 1 << 10);
+    let mut queries = ranges.iter().cycle();
+    b.iter(|| tree.query(queries.next().unwrap().clone()));
+}
+
+#[bench]
+fn apply_mut_1e6(b: &mut test::Bencher) {
+    let mut tree = Tree::build(1_000_000, |i| (Sum(i as i64), Size(1)));
+    let ranges = random_ranges(1_000_000, 1 << 10);
+    let mut updates = ranges.iter().cycle();
+    b.iter(|| tree.apply_mut(updates.next().unwrap().clone(), Plus(1)));
+}
+
+#[bench]
+fn apply_1e6(b: &mut test::Bencher) {
+    let mut tree = Tree::build(1_000_000, |i| (Sum(i as i64), Size(1)));
+    let ranges = random_ranges(1_000_000, 1 << 10);
+    let mut updates = ranges.iter().cycle();
+    b.iter(|| tree = tree.apply(updates.next().unwrap().clone(), Plus(1)));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_build() {
+    fn shape<V, M, R: SharedRef>(tree: &SegTree<V, M, R>) -> Vec<usize> {
+        let mut sizes = vec![];
+        let mut stack = vec![tree];
+        while let Some(node) = stack.pop() {
+            sizes.push(node.size());
+            if let SegTree::Branch { left, right, .. } = node {
+                stack.push(right);
+                stack.push(left);
+            }
+        }
+        sizes
+    }
+    for len in [0, 1, 5, 1 << 14, (1 << 16) + 3, 100_000] {
+        let init = |i: usize| (Sum(i as i64 % 7), Size(1));
+        let parallel = ArcSegTree::<_, Plus<i64>>::par_build(len, init);
+        let sequential = ArcSegTree::<_, Plus<i64>>::build(len, init);
+        assert_eq!(parallel, sequential);
+        assert_eq!(shape(&parallel), shape(&sequential));
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[bench]
+fn par_build_1e6(b: &mut test::Bencher) {
+    b.iter(|| ArcSegTree::<_, Plus<i64>>::par_build(1_000_000, |i| (Sum(i as i64), Size(1))));
+}
+
+#[cfg(feature = "rayon")]
+#[bench]
+fn arc_build_1e6(b: &mut test::Bencher) {
+    b.iter(|| ArcSegTree::<_, Plus<i64>>::build(1_000_000, |i| (Sum(i as i64), Size(1))));
+}
+
+#[cfg(feature = "dot")]
+#[test]
+fn to_dot() {
+    let tree: SegTree<_, Plus<i64>> = [1, 2, 3].into_iter().map(Max::Has).collect();
+    let tree = tree.apply(0..2, Plus(1));
+    let expected = r#"digraph SegTree {
+    node [shape=box];
+    n0 [label="0..3\nvalue: 3\nmodifier: +0"];
+    n0 -> n1;
+    n1 [label="0..2\nvalue: 3\nmodifier: +1"];
+    n1 -> n2;
+    n2 [label="0..1\nvalue: 1"];
+    n1 -> n3;
+    n3 [label="1..2\nvalue: 2"];
+    n0 -> n4;
+    n4 [label="2..3\nvalue: 3"];
+}
+"#;
+    assert_eq!(tree.to_dot(), expected);
+    assert_eq!(
+        SegTree::<Max<i64>, Plus<i64>>::Empty.to_dot(),
+        "digraph SegTree {\n    node [shape=box];\n}\n"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    let trees = [
+        Tree::Empty,
+        Tree::Unit((Sum(7), Size(1))),
+        build(&[3, 1, 4, 1, 5, 9, 2]).apply(2..6, Plus(10)),
+    ];
+    for tree in trees {
+        let json = serde_json::to_string(&tree).unwrap();
+        let restored: Tree = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.size(), tree.size());
+        for l in 0..tree.size() {
+            for r in l..=tree.size() {
+                assert_eq!(sum(&restored, l, r), sum(&tree, l, r));
+            }
+        }
+    }
+}
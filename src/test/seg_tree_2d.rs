@@ -0,0 +1,39 @@
+use super::Rng;
+use crate::data_structure::seg_tree_2d::*;
+use crate::seg_tree::monoids::*;
+
+#[test]
+fn against_brute_force() {
+    let mut rng = Rng(0xA0761D6478BD642F);
+    for (width, height) in [(1, 1), (3, 5), (8, 2), (7, 7)] {
+        let mut grid: Vec<Vec<i64>> = (0..width)
+            .map(|_| (0..height).map(|_| rng.below(100) as i64).collect())
+            .collect();
+        let mut sum = SegTree2D::build(width, height, |x, y| Sum(grid[x][y]));
+        let mut max = SegTree2D::build(width, height, |x, y| Max::Has(grid[x][y]));
+        for _ in 0..200 {
+            let (x, y, v) = (rng.below(width), rng.below(height), rng.below(100) as i64);
+            grid[x][y] = v;
+            sum.set(x, y, Sum(v));
+            max.set(x, y, Max::Has(v));
+            assert_eq!(sum.get(x, y), Sum(v));
+
+            let x0 = rng.below(width + 1);
+            let x1 = x0 + rng.below(width - x0 + 1);
+            let y0 = rng.below(height + 1);
+            let y1 = y0 + rng.below(height - y0 + 1);
+            let cells = grid[x0..x1].iter().flat_map(|column| &column[y0..y1]);
+            assert_eq!(sum.query(x0..x1, y0..y1), Sum(cells.clone().sum()));
+            assert_eq!(
+                max.query(x0..x1, y0..y1),
+                cells.max().map_or(Max::NegInf, |&v| Max::Has(v))
+            );
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "(2, 0) out of bounds for grid of size 2x3")]
+fn set_out_of_bounds() {
+    SegTree2D::build(2, 3, |_, _| Sum(0)).set(2, 0, Sum(1));
+}
@@ -0,0 +1,72 @@
+use super::Rng;
+use crate::data_structure::sparse_seg_tree::*;
+use crate::seg_tree::monoids::*;
+
+type Tree = SparseSegTree<(Sum<i128>, Size), Plus<i128>>;
+
+fn blank(n: u64) -> (Sum<i128>, Size) {
+    (Sum(0), Size(n as usize))
+}
+
+#[test]
+fn against_brute_force() {
+    let mut rng = Rng(0xBF58476D1CE4E5B9);
+    for len in [0u64, 1, 2, 3, 10, 37] {
+        let mut tree = Tree::with_blank(len, blank);
+        let mut values = vec![0i128; len as usize];
+        let mut versions = vec![(tree.clone(), values.clone())];
+        for _ in 0..100 {
+            let l = rng.below(len as usize + 1);
+            let r = l + rng.below(len as usize - l + 1);
+            let d = rng.below(21) as i128 - 10;
+            tree = tree.apply(l as u64..r as u64, Plus(d));
+            for v in &mut values[l..r] {
+                *v += d;
+            }
+            versions.push((tree.clone(), values.clone()));
+        }
+        // every version still answers for its own updates
+        for (tree, values) in &versions {
+            for l in 0..=values.len() {
+                for r in l..=values.len() {
+                    let (sum, size) = tree.query(l as u64..r as u64);
+                    assert_eq!(sum.0, values[l..r].iter().sum::<i128>());
+                    assert_eq!(size.0, r - l);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn huge_domain() {
+    const N: u64 = 1_000_000_000_000_000_000;
+    let tree = Tree::with_blank(N, blank)
+        .apply(..N / 2, Plus(2))
+        .apply(N / 4..=N / 4, Plus(1_000))
+        .apply(N - 3.., Plus(-1));
+    assert_eq!(tree.len(), N);
+    assert_eq!(tree.query(..).0 .0, N as i128 + 1_000 - 3);
+    assert_eq!(tree.query(N / 4..N / 4 + 2).0 .0, 2 + 1_002);
+    assert_eq!(tree.query(N / 2..N - 3), blank(N / 2 - 3));
+    assert_eq!(tree.query(N - 1..).0 .0, -1);
+    // only the paths of the updates are allocated
+    assert!(tree.node_count() <= 3 * 2 * 64);
+}
+
+#[test]
+fn blank_is_empty_by_default() {
+    let tree: SparseSegTree<Max<i64>, Plus<i64>> = SparseSegTree::new(1 << 40);
+    assert_eq!(tree.query(..), Max::NegInf);
+    let tree = tree.apply(..1 << 20, Plus(5));
+    // nothing was there to add to
+    assert_eq!(tree.query(..), Max::NegInf);
+    assert!(tree.node_count() <= 2 * 40);
+    assert!(SparseSegTree::<Max<i64>, Plus<i64>>::new(0).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "out of bounds")]
+fn query_out_of_bounds() {
+    Tree::with_blank(10, blank).query(5..11);
+}
@@ -0,0 +1,34 @@
+use crate::data_structure::sparse_table::*;
+use crate::traits::semigroup::*;
+
+#[test]
+fn query() {
+    let values = [3u64, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5];
+    let max: SparseTable<_> = values.iter().map(|&v| Max::Has(v)).collect();
+    let gcd: SparseTable<_> = values.iter().map(|&v| Gcd(v * 6)).collect();
+    assert_eq!(max.size(), values.len());
+    for l in 0..=values.len() {
+        for r in l..=values.len() {
+            let expected = values[l..r].iter().copied().map(Max::Has).max();
+            assert_eq!(max.query(l..r), expected.unwrap_or(Max::NegInf));
+            let expected = values[l..r]
+                .iter()
+                .fold(Gcd(0), |acc, &v| acc.merge(Gcd(v * 6)));
+            assert_eq!(gcd.query(l..r), expected);
+        }
+    }
+}
+
+#[test]
+fn bit_and_or() {
+    let values = [0b1110u32, 0b0111, 0b1101, 0b0101, 0b1111];
+    let and: SparseTable<_> = values.iter().map(|&v| BitAnd(v)).collect();
+    let or: SparseTable<_> = values.iter().map(|&v| BitOr(v)).collect();
+    for l in 0..=values.len() {
+        for r in l..=values.len() {
+            let range = &values[l..r];
+            assert_eq!(and.query(l..r), BitAnd(range.iter().fold(!0, |a, v| a & v)));
+            assert_eq!(or.query(l..r), BitOr(range.iter().fold(0, |a, v| a | v)));
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use super::monoid::*;
+use super::semigroup::*;
+
+/// `a.merge(a.inverse()) == a.inverse().merge(a) == empty()`
+///
+/// `Max`/`Min` and the like have no inverse and must not implement this.
+pub trait Group
+where
+    Self: Monoid,
+{
+    fn inverse(self) -> Self;
+}
+
+macro_rules! impl_tuple_group {
+    ($(($t:ident, $i:tt)),*) => {
+        impl<$($t: Group),*> Group for ($($t,)*) {
+            #[inline]
+            fn inverse(self) -> Self {
+                ($(self.$i.inverse(),)*)
+            }
+        }
+    };
+}
+
+impl_tuple_group!((A, 0), (B, 1));
+impl_tuple_group!((A, 0), (B, 1), (C, 2));
+impl_tuple_group!((A, 0), (B, 1), (C, 2), (D, 3));
+impl_tuple_group!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+
+macro_rules! impl_num_group {
+    [$($t:ty),*] => {
+        $(
+        impl Group for Sum<$t> {
+            #[inline]
+            fn inverse(self) -> Self {
+                Sum(-self.0)
+            }
+        }
+        )*
+    };
+}
+
+macro_rules! impl_int_group {
+    [$($t:ty),*] => {
+        $(
+        impl Group for Xor<$t> {
+            /// Every value is its own inverse
+            #[inline]
+            fn inverse(self) -> Self {
+                self
+            }
+        }
+        )*
+    };
+}
+
+impl_num_group![i8, i16, i32, i64, i128, isize, f32, f64];
+impl_int_group![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize];
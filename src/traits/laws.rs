@@ -0,0 +1,69 @@
+//! Checks of the laws the traits document, for the tests of custom monoids
+//!
+//! A [`SegTree`](crate::seg_tree::SegTree) over a value that breaks them
+//! gives wrong answers without any other sign, so call these on a handful
+//! of samples, including edge cases like zeros and extremes:
+//!
+//! ```
+//! use solution::seg_tree::monoids::*;
+//! use solution::traits::laws::*;
+//!
+//! assert_monoid_laws(&[Max::Has(3), Max::Has(-1), Max::NegInf]);
+//! ```
+//!
+//! Enabled for the crate's own tests and by the `testing` feature.
+
+use super::{Monoid, Semigroup};
+
+use core::fmt::Debug;
+
+/// Assert `a.merge(b.merge(c)) == a.merge(b).merge(c)` over all triples of `samples`
+///
+/// # Panics
+///
+/// Panics with the offending triple if any of them is not associative.
+pub fn assert_semigroup_laws<V: Semigroup + Clone + PartialEq + Debug>(samples: &[V]) {
+    for a in samples {
+        for b in samples {
+            for c in samples {
+                let right = a.clone().merge(b.clone().merge(c.clone()));
+                let left = a.clone().merge(b.clone()).merge(c.clone());
+                assert!(
+                    left == right,
+                    "merge is not associative for {:?}, {:?}, {:?}: {:?} != {:?}",
+                    a,
+                    b,
+                    c,
+                    left,
+                    right
+                );
+            }
+        }
+    }
+}
+
+/// Assert the semigroup laws, `empty()` being an identity on both sides,
+/// and `is_identity` holding for nothing but `empty()`
+///
+/// # Panics
+///
+/// Panics with the offending sample if any law fails.
+pub fn assert_monoid_laws<V: Monoid + Clone + PartialEq + Debug>(samples: &[V]) {
+    assert_semigroup_laws(samples);
+    for a in samples.iter().chain([&V::empty()]) {
+        let left = V::empty().merge(a.clone());
+        let right = a.clone().merge(V::empty());
+        assert!(
+            left == *a && right == *a,
+            "empty() is not an identity for {:?}: {:?} on the left, {:?} on the right",
+            a,
+            left,
+            right
+        );
+        assert!(
+            !a.is_identity() || *a == V::empty(),
+            "{:?} is not empty() but is_identity() says it is",
+            a
+        );
+    }
+}
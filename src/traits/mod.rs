@@ -1,7 +1,13 @@
 pub mod semigroup;
-pub use semigroup::Semigroup;
+pub use semigroup::{CommutativeSemigroup, Idempotent, Semigroup};
 
 pub mod monoid;
 pub use monoid::Monoid;
 
+pub mod group;
+pub use group::Group;
+
 pub mod foldable;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod laws;
@@ -6,15 +6,42 @@ where
     Self: Semigroup,
 {
     fn empty() -> Self;
-}
 
-impl<A: Monoid, B: Monoid> Monoid for (A, B) {
+    /// Whether `self` is `Self::empty()`
+    ///
+    /// Only used to skip the work for no-ops, so the default `false` is always correct.
     #[inline]
-    fn empty() -> Self {
-        (A::empty(), B::empty())
+    fn is_identity(&self) -> bool {
+        false
     }
 }
 
+macro_rules! impl_tuple_monoid {
+    ($(($t:ident, $i:tt)),*) => {
+        impl<$($t: Monoid),*> Monoid for ($($t,)*) {
+            #[inline]
+            fn empty() -> Self {
+                ($($t::empty(),)*)
+            }
+
+            #[inline]
+            fn is_identity(&self) -> bool {
+                $(self.$i.is_identity())&&*
+            }
+        }
+    };
+}
+
+impl_tuple_monoid!((A, 0), (B, 1));
+impl_tuple_monoid!((A, 0), (B, 1), (C, 2));
+impl_tuple_monoid!((A, 0), (B, 1), (C, 2), (D, 3));
+impl_tuple_monoid!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+
+/// Any semigroup lifted to a monoid, with `None` as the identity
+///
+/// `None` merged with `a` on either side gives `a`, and `Some(a).merge(Some(b))`
+/// is `Some(a.merge(b))`. So a `SegTree` of `Option<T>` queries `None` on an
+/// empty range, without having to pick a sentinel as `Max`/`Min` do.
 impl<T: Semigroup> Semigroup for Option<T> {
     fn merge(self, other: Self) -> Self {
         match (self, other) {
@@ -24,14 +51,22 @@ impl<T: Semigroup> Semigroup for Option<T> {
         }
     }
 }
+impl<T: CommutativeSemigroup> CommutativeSemigroup for Option<T> {}
+impl<T: Idempotent> Idempotent for Option<T> {}
 impl<T: Semigroup> Monoid for Option<T> {
     #[inline]
     fn empty() -> Self {
         None
     }
+
+    #[inline]
+    fn is_identity(&self) -> bool {
+        self.is_none()
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Size(pub usize);
 impl Semigroup for Size {
     #[inline]
@@ -39,6 +74,7 @@ impl Semigroup for Size {
         Size(self.0 + other.0)
     }
 }
+impl CommutativeSemigroup for Size {}
 impl Default for Size {
     #[inline]
     fn default() -> Self {
@@ -53,6 +89,11 @@ macro_rules! impl_monoid {
             fn empty() -> $t {
                 $v
             }
+
+            #[inline]
+            fn is_identity(&self) -> bool {
+                *self == $v
+            }
         }
     };
 }
@@ -61,21 +102,74 @@ macro_rules! impl_num_monoid {
         $(
         impl_monoid!(Sum<$t>, Sum(0 as $t));
         impl_monoid!(Product<$t>, Product(1 as $t));
+
+        impl<const N: usize> Monoid for Matrix<N, $t> {
+            /// The identity matrix
+            #[inline]
+            fn empty() -> Self {
+                Matrix(core::array::from_fn(|i| {
+                    core::array::from_fn(|j| if i == j { 1 as $t } else { 0 as $t })
+                }))
+            }
+        }
         )*
     };
 }
 
-impl_num_monoid![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, f32, f64];
+macro_rules! impl_int_monoid {
+    [$($t:ty),*] => {
+        $(
+        impl_monoid!(Xor<$t>, Xor(0));
+        impl_monoid!(CheckedSum<$t>, CheckedSum(0));
+        impl_monoid!(BitAnd<$t>, BitAnd(!0));
+        impl_monoid!(BitOr<$t>, BitOr(0));
+        )*
+    };
+}
+
+impl_num_monoid![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize, f32, f64];
+macro_rules! impl_unsigned_monoid {
+    [$($t:ty),*] => {
+        $(
+        impl_monoid!(Gcd<$t>, Gcd(0));
+        impl_monoid!(Lcm<$t>, Lcm(1));
+        )*
+    };
+}
+
+impl_int_monoid![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize];
+impl_unsigned_monoid![u8, u16, u32, u64, u128, usize];
 impl_monoid!(Size, Size(0));
+impl<T> Monoid for Single<T> {
+    #[inline]
+    fn empty() -> Self {
+        Single(None)
+    }
+
+    #[inline]
+    fn is_identity(&self) -> bool {
+        self.0.is_none()
+    }
+}
 impl<T: Ord> Monoid for Min<T> {
     #[inline]
     fn empty() -> Self {
         Min::Inf
     }
+
+    #[inline]
+    fn is_identity(&self) -> bool {
+        matches!(self, Min::Inf)
+    }
 }
 impl<T: Ord> Monoid for Max<T> {
     #[inline]
     fn empty() -> Self {
         Max::NegInf
     }
+
+    #[inline]
+    fn is_identity(&self) -> bool {
+        matches!(self, Max::NegInf)
+    }
 }
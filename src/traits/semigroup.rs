@@ -1,16 +1,51 @@
-use std::ops::{Add, Mul};
+use core::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    hash::{Hash, Hasher},
+    ops::{Add, BitXor, Mul},
+};
 
 /// `a.merge(&b.merge(&c)) == a.merge(&b).merge(&c)`
 pub trait Semigroup {
     fn merge(self, other: Self) -> Self;
 }
 
-impl<A: Semigroup, B: Semigroup> Semigroup for (A, B) {
-    fn merge(self, (oa, ob): Self) -> Self {
-        let (a, b) = self;
-        (A::merge(a, oa), B::merge(b, ob))
-    }
+/// `a.merge(b) == b.merge(a)`
+pub trait CommutativeSemigroup
+where
+    Self: Semigroup,
+{
+}
+
+/// `a.merge(a) == a`
+///
+/// Merging overlapping ranges gives the right answer,
+/// which is what `SparseTable` relies on.
+pub trait Idempotent
+where
+    Self: Semigroup,
+{
+}
+
+/// Tuples merge component-wise
+macro_rules! impl_tuple_semigroup {
+    ($(($t:ident, $i:tt)),*) => {
+        impl<$($t: Semigroup),*> Semigroup for ($($t,)*) {
+            #[inline]
+            fn merge(self, other: Self) -> Self {
+                ($($t::merge(self.$i, other.$i),)*)
+            }
+        }
+        impl<$($t: CommutativeSemigroup),*> CommutativeSemigroup for ($($t,)*) {}
+        impl<$($t: Idempotent),*> Idempotent for ($($t,)*) {}
+    };
 }
+
+impl_tuple_semigroup!((A, 0), (B, 1));
+impl_tuple_semigroup!((A, 0), (B, 1), (C, 2));
+impl_tuple_semigroup!((A, 0), (B, 1), (C, 2), (D, 3));
+impl_tuple_semigroup!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+
 macro_rules! impl_semigroup {
     ($t:ty,$k:path,$v:expr) => {
         impl<T: $k> Semigroup for $t {
@@ -22,7 +57,8 @@ macro_rules! impl_semigroup {
     };
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sum<T: Add<Output = T>>(pub T);
 impl<T: Add<Output = T>> Add for Sum<T> {
     type Output = Self;
@@ -33,7 +69,40 @@ impl<T: Add<Output = T>> Add for Sum<T> {
 }
 impl_semigroup!(Sum<T>, Add<Output = T>, <Sum<T> as Add>::add);
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
+/// A [`Sum`] of integers that panics instead of overflowing
+///
+/// Every merge goes through `checked_add`, which costs a compare and a
+/// branch on each merge compared to the plain `Sum`. Meant for catching
+/// overflows while testing locally, switch back to `Sum` once the bounds
+/// are known to hold.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CheckedSum<T>(pub T);
+
+macro_rules! impl_checked_sum {
+    [$($t:ty),*] => {
+        $(
+        impl Semigroup for CheckedSum<$t> {
+            /// # Panics
+            ///
+            /// Panics if the sum overflows.
+            #[inline]
+            fn merge(self, other: Self) -> Self {
+                match self.0.checked_add(other.0) {
+                    Some(sum) => CheckedSum(sum),
+                    None => panic!("CheckedSum overflowed: {} + {}", self.0, other.0),
+                }
+            }
+        }
+        impl CommutativeSemigroup for CheckedSum<$t> {}
+        )*
+    };
+}
+
+impl_checked_sum![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize];
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Product<T: Mul<Output = T>>(pub T);
 impl<T: Mul<Output = T>> Mul for Product<T> {
     type Output = Self;
@@ -44,16 +113,257 @@ impl<T: Mul<Output = T>> Mul for Product<T> {
 }
 impl_semigroup!(Product<T>, Mul<Output = T>, <Product<T> as Mul>::mul);
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Xor<T: BitXor<Output = T>>(pub T);
+impl<T: BitXor<Output = T>> BitXor for Xor<T> {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Xor(self.0 ^ rhs.0)
+    }
+}
+impl_semigroup!(Xor<T>, BitXor<Output = T>, <Xor<T> as BitXor>::bitxor);
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Max<O: Ord> {
     NegInf,
     Has(O),
 }
 impl_semigroup!(Max<T>, Ord, <Max<T> as Ord>::max);
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Min<O: Ord> {
     Has(O),
     Inf,
 }
 impl_semigroup!(Min<T>, Ord, <Min<T> as Ord>::min);
+
+impl<T: Ord> CommutativeSemigroup for Max<T> {}
+impl<T: Ord> CommutativeSemigroup for Min<T> {}
+impl<T: Ord> Idempotent for Max<T> {}
+impl<T: Ord> Idempotent for Min<T> {}
+
+/// A float ordered by `total_cmp`, so that it can go into [`Max`] and [`Min`]
+///
+/// The identities stay the `Max::NegInf` and `Min::Inf` sentinels, below
+/// and above every float including the infinities. NaNs take part in the
+/// order instead of breaking it: a positive NaN is greater than `INFINITY`
+/// and a negative NaN less than `NEG_INFINITY`, so `Max` of a range holding
+/// `NAN` is `NAN` while `Min` never picks it. `-0.0` is less than `0.0`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TotalOrd<F>(pub F);
+
+macro_rules! impl_total_ord {
+    [$($t:ty),*] => {
+        $(
+        impl PartialEq for TotalOrd<$t> {
+            fn eq(&self, other: &Self) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+        impl Eq for TotalOrd<$t> {}
+        impl PartialOrd for TotalOrd<$t> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for TotalOrd<$t> {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.total_cmp(&other.0)
+            }
+        }
+        impl Hash for TotalOrd<$t> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.to_bits().hash(state)
+            }
+        }
+        )*
+    };
+}
+
+impl_total_ord![f32, f64];
+
+/// An `N` by `N` matrix, merged by multiplication
+///
+/// `a.merge(b)` is `a * b`, so a range of matrices is worth their product
+/// from left to right, e.g. for evaluating linear recurrences.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct Matrix<const N: usize, T>(pub [[T; N]; N]);
+
+impl<const N: usize, T: Copy + Add<Output = T> + Mul<Output = T>> Semigroup for Matrix<N, T> {
+    fn merge(self, other: Self) -> Self {
+        let (a, b) = (self.0, other.0);
+        Matrix(core::array::from_fn(|i| {
+            core::array::from_fn(|j| {
+                (1..N).fold(a[i][0] * b[0][j], |acc, k| acc + a[i][k] * b[k][j])
+            })
+        }))
+    }
+}
+
+/// The last of the merged values, `Single(None)` is the identity
+///
+/// Merging keeps the right-hand value, so a range of them is worth its last element.
+/// It is mostly useful to store plain values in a `SegTree`.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Single<T>(pub Option<T>);
+
+impl<T> Semigroup for Single<T> {
+    #[inline]
+    fn merge(self, other: Self) -> Self {
+        Single(other.0.or(self.0))
+    }
+}
+impl<T> Idempotent for Single<T> {}
+
+/// Greatest common divisor of unsigned integers, `Gcd(0)` is the identity
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gcd<T>(pub T);
+
+/// Least common multiple of unsigned integers, `Lcm(1)` is the identity
+///
+/// The lcm grows quickly and `merge` panics on overflow,
+/// so pick a wide enough type (e.g. `u128`) for the data.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lcm<T>(pub T);
+
+macro_rules! impl_gcd_lcm {
+    [$($t:ty),*] => {
+        $(
+        impl Semigroup for Gcd<$t> {
+            #[inline]
+            fn merge(self, other: Self) -> Self {
+                let (mut a, mut b) = (self.0, other.0);
+                while b != 0 {
+                    (a, b) = (b, a % b);
+                }
+                Gcd(a)
+            }
+        }
+        impl Semigroup for Lcm<$t> {
+            #[inline]
+            fn merge(self, other: Self) -> Self {
+                if self.0 == 0 || other.0 == 0 {
+                    return Lcm(0);
+                }
+                let gcd = Gcd(self.0).merge(Gcd(other.0)).0;
+                Lcm((self.0 / gcd).checked_mul(other.0).expect("lcm overflow"))
+            }
+        }
+        )*
+    };
+}
+
+impl_gcd_lcm![u8, u16, u32, u64, u128, usize];
+
+/// Bitwise and of integers, all ones is the identity
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitAnd<T>(pub T);
+
+/// Bitwise or of integers, `BitOr(0)` is the identity
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitOr<T>(pub T);
+
+macro_rules! impl_bit_and_or {
+    [$($t:ty),*] => {
+        $(
+        impl Semigroup for BitAnd<$t> {
+            #[inline]
+            fn merge(self, other: Self) -> Self {
+                BitAnd(self.0 & other.0)
+            }
+        }
+        impl Semigroup for BitOr<$t> {
+            #[inline]
+            fn merge(self, other: Self) -> Self {
+                BitOr(self.0 | other.0)
+            }
+        }
+        )*
+    };
+}
+
+impl_bit_and_or![u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize];
+
+macro_rules! impl_commutative {
+    ($t:ident, [$($n:ty),*]) => {
+        $(
+        impl CommutativeSemigroup for $t<$n> {}
+        )*
+    };
+}
+
+impl_commutative!(
+    Sum,
+    [u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize, f32, f64]
+);
+impl_commutative!(
+    Product,
+    [u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize, f32, f64]
+);
+impl_commutative!(
+    Xor,
+    [u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize]
+);
+impl_commutative!(
+    BitAnd,
+    [u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize]
+);
+impl_commutative!(
+    BitOr,
+    [u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize]
+);
+impl_commutative!(Gcd, [u8, u16, u32, u64, u128, usize]);
+impl_commutative!(Lcm, [u8, u16, u32, u64, u128, usize]);
+
+impl<T> Idempotent for BitAnd<T> where Self: Semigroup {}
+impl<T> Idempotent for BitOr<T> where Self: Semigroup {}
+impl<T> Idempotent for Gcd<T> where Self: Semigroup {}
+impl<T> Idempotent for Lcm<T> where Self: Semigroup {}
+
+macro_rules! impl_display {
+    ($t:ident, $($bound:tt)*) => {
+        impl<T: Display + $($bound)*> Display for $t<T> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+impl_display!(Sum, Add<Output = T>);
+impl_display!(Product, Mul<Output = T>);
+impl_display!(CheckedSum, Sized);
+impl_display!(Xor, BitXor<Output = T>);
+impl_display!(BitAnd, Sized);
+impl_display!(BitOr, Sized);
+impl_display!(Gcd, Sized);
+impl_display!(Lcm, Sized);
+impl_display!(TotalOrd, Sized);
+
+impl<T: Ord + Display> Display for Max<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Max::NegInf => f.write_str("-inf"),
+            Max::Has(x) => x.fmt(f),
+        }
+    }
+}
+
+impl<T: Ord + Display> Display for Min<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Min::Inf => f.write_str("inf"),
+            Min::Has(x) => x.fmt(f),
+        }
+    }
+}